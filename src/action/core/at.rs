@@ -0,0 +1,333 @@
+use crate::action::{Action, ActionSignal, Props, StatefulAction, DEFAULT, INFINITE, VISUAL};
+use crate::comm::{QWriter, Signal, SignalId};
+use crate::resource::{IoManager, LoggerSignal, ResourceAddr, ResourceManager};
+use crate::server::{block_start, AsyncSignal, Config, State, SyncSignal};
+use crate::util::spin_sleeper;
+use eframe::egui;
+use eframe::egui::Response;
+use eyre::{eyre, Result};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use serde_cbor::Value;
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The timer thread can be cancelled until this long before an onset, after which it spins.
+const SPIN_MARGIN: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct At {
+    children: Vec<(f32, Box<dyn Action>)>,
+    #[serde(default)]
+    clock: ClockRef,
+    #[serde(default = "defaults::group")]
+    group: String,
+}
+
+stateful!(At {
+    pending: VecDeque<(usize, Duration, Box<dyn StatefulAction>)>,
+    active: Vec<Box<dyn StatefulAction>>,
+    clock: ClockRef,
+    group: String,
+    reference: Option<Instant>,
+    due: Arc<Mutex<usize>>,
+    started: usize,
+    timer: Option<Sender<()>>,
+});
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClockRef {
+    Block,
+    #[default]
+    Parent,
+}
+
+mod defaults {
+    #[inline(always)]
+    pub fn group() -> String {
+        "at".to_owned()
+    }
+}
+
+impl Action for At {
+    fn init(self) -> Result<Box<dyn Action>>
+    where
+        Self: 'static + Sized,
+    {
+        for (onset, _) in self.children.iter() {
+            if !onset.is_finite() || *onset < 0.0 {
+                return Err(eyre!(
+                    "Onsets of `At` children should be non-negative numbers ({onset})."
+                ));
+            }
+        }
+
+        if self.group.is_empty() {
+            return Err(eyre!("`At` requires a non-empty log `group`."));
+        }
+
+        Ok(Box::new(self))
+    }
+
+    #[inline]
+    fn in_signals(&self) -> BTreeSet<SignalId> {
+        let mut signals = BTreeSet::new();
+        for (_, c) in self.children.iter() {
+            signals.extend(c.in_signals());
+        }
+        signals
+    }
+
+    #[inline]
+    fn out_signals(&self) -> BTreeSet<SignalId> {
+        let mut signals = BTreeSet::new();
+        for (_, c) in self.children.iter() {
+            signals.extend(c.out_signals());
+        }
+        signals
+    }
+
+    #[inline]
+    fn resources(&self, config: &Config) -> Vec<ResourceAddr> {
+        self.children
+            .iter()
+            .flat_map(|(_, c)| c.resources(config))
+            .unique()
+            .collect()
+    }
+
+    fn stateful(
+        &self,
+        io: &IoManager,
+        res: &ResourceManager,
+        config: &Config,
+        sync_writer: &QWriter<SyncSignal>,
+        async_writer: &QWriter<AsyncSignal>,
+    ) -> Result<Box<dyn StatefulAction>> {
+        let mut pending = vec![];
+        for (i, (onset, c)) in self.children.iter().enumerate() {
+            pending.push((
+                i,
+                Duration::from_secs_f32(*onset),
+                c.stateful(io, res, config, sync_writer, async_writer)?,
+            ));
+        }
+
+        // Children keep their declared index (used in the log) once sorted by onset
+        pending.sort_by_key(|(_, onset, _)| *onset);
+
+        Ok(Box::new(StatefulAt {
+            done: false,
+            pending: pending.into(),
+            active: vec![],
+            clock: self.clock,
+            group: self.group.clone(),
+            reference: None,
+            due: Arc::new(Mutex::new(0)),
+            started: 0,
+            timer: None,
+        }))
+    }
+}
+
+impl StatefulAt {
+    /// Starts the children whose onsets have been reached and logs their requested and
+    /// actual onsets (in seconds) relative to the reference clock.
+    fn start_due(
+        &mut self,
+        sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        state: &State,
+    ) -> Result<Signal> {
+        let reference = self.reference.unwrap();
+        let due = *self.due.lock().unwrap();
+
+        let mut news = vec![];
+        while self.started < due {
+            let (i, onset, mut c) = match self.pending.pop_front() {
+                Some(c) => c,
+                None => break,
+            };
+
+            let actual = Instant::now().saturating_duration_since(reference);
+            news.extend(c.start(sync_writer, async_writer, state)?);
+            async_writer.push(LoggerSignal::Append(
+                self.group.clone(),
+                (
+                    format!("{i}:{}", c.type_str()),
+                    Value::Array(vec![
+                        Value::Float(onset.as_secs_f64()),
+                        Value::Float(actual.as_secs_f64()),
+                    ]),
+                ),
+            ));
+
+            self.started += 1;
+            if !c.is_over()? {
+                self.active.push(c);
+            }
+        }
+
+        Ok(news.into())
+    }
+}
+
+impl StatefulAction for StatefulAt {
+    impl_stateful!();
+
+    #[inline]
+    fn props(&self) -> Props {
+        let mut props = DEFAULT;
+
+        for c in self.active.iter() {
+            let c = c.props();
+            if c.visual() {
+                props |= VISUAL;
+            }
+            if c.infinite() {
+                props |= INFINITE;
+            }
+        }
+
+        for (_, _, c) in self.pending.iter() {
+            if c.props().infinite() {
+                props |= INFINITE;
+            }
+        }
+
+        props.into()
+    }
+
+    fn start(
+        &mut self,
+        sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        state: &State,
+    ) -> Result<Signal> {
+        if self.pending.is_empty() {
+            self.done = true;
+            sync_writer.push(SyncSignal::UpdateGraph);
+            return Ok(Signal::none());
+        }
+
+        let reference = match self.clock {
+            ClockRef::Block => block_start().unwrap_or_else(Instant::now),
+            ClockRef::Parent => Instant::now(),
+        };
+        self.reference = Some(reference);
+
+        let onsets: Vec<_> = self.pending.iter().map(|(_, onset, _)| *onset).collect();
+        let elapsed = Instant::now().saturating_duration_since(reference);
+        let n_due = onsets.iter().filter(|&&onset| onset <= elapsed).count();
+        *self.due.lock().unwrap() = n_due;
+
+        if n_due < onsets.len() {
+            let due = self.due.clone();
+            let mut sync_writer = sync_writer.clone();
+            let (tx_timer, rx_timer) = mpsc::channel();
+            thread::spawn(move || {
+                let sleeper = spin_sleeper();
+                for (i, onset) in onsets.into_iter().enumerate().skip(n_due) {
+                    // Exit as soon as the action is stopped or dropped (i.e. the sender is gone)
+                    let target = reference + onset;
+                    let wait = target
+                        .saturating_duration_since(Instant::now())
+                        .saturating_sub(SPIN_MARGIN);
+                    if !matches!(rx_timer.recv_timeout(wait), Err(RecvTimeoutError::Timeout)) {
+                        break;
+                    }
+
+                    let now = Instant::now();
+                    if target > now {
+                        sleeper.sleep(target - now);
+                    }
+                    *due.lock().unwrap() = i + 1;
+                    sync_writer.push(SyncSignal::UpdateGraph);
+                }
+            });
+            self.timer = Some(tx_timer);
+        }
+
+        let news = self.start_due(sync_writer, async_writer, state)?;
+        if self.pending.is_empty() && self.active.is_empty() {
+            self.done = true;
+            sync_writer.push(SyncSignal::UpdateGraph);
+        }
+        Ok(news)
+    }
+
+    fn update(
+        &mut self,
+        signal: &ActionSignal,
+        sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        state: &State,
+    ) -> Result<Signal> {
+        let mut news = vec![];
+        let mut done = vec![];
+        for (i, c) in self.active.iter_mut().enumerate() {
+            news.extend(c.update(signal, sync_writer, async_writer, state)?);
+
+            if c.is_over()? {
+                done.push(i);
+            }
+        }
+        for i in done.into_iter().rev() {
+            self.active.remove(i);
+        }
+
+        news.extend(self.start_due(sync_writer, async_writer, state)?);
+
+        if self.pending.is_empty() && self.active.is_empty() {
+            self.done = true;
+        }
+
+        Ok(news.into())
+    }
+
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        state: &State,
+    ) -> Result<Response> {
+        // Only the most recently started visual child is shown
+        if let Some(c) = self.active.iter_mut().rev().find(|c| c.props().visual()) {
+            c.show(ui, sync_writer, async_writer, state)
+        } else {
+            Ok(ui.label(""))
+        }
+    }
+
+    fn stop(
+        &mut self,
+        sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        state: &State,
+    ) -> Result<Signal> {
+        let mut news = vec![];
+        for c in self.active.iter_mut() {
+            news.extend(c.stop(sync_writer, async_writer, state)?);
+        }
+        self.pending.clear();
+        self.timer.take();
+        Ok(news.into())
+    }
+
+    fn debug(&self) -> Vec<(&str, String)> {
+        <dyn StatefulAction>::debug(self)
+            .into_iter()
+            .chain([
+                ("clock", format!("{:?}", self.clock)),
+                ("started", format!("{:?}", self.started)),
+                ("pending", format!("{:?}", self.pending.len())),
+            ])
+            .collect()
+    }
+}
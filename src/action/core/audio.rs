@@ -3,8 +3,8 @@
 use crate::action::{Action, ActionSignal, Props, StatefulAction, DEFAULT, INFINITE};
 use crate::comm::{QWriter, Signal, SignalId};
 use crate::resource::{
-    AudioChannel, AudioSink, IoManager, ResourceAddr, ResourceManager, ResourceValue,
-    TimePrecision, Volume,
+    AudioBuffer, AudioChannel, AudioRouting, AudioSink, Fade, IoManager, OptionalPath,
+    ResourceAddr, ResourceManager, ResourceValue, Synth, TimePrecision, Volume,
 };
 use crate::server::{AsyncSignal, Config, State, SyncSignal};
 use crate::util::spin_sleeper;
@@ -30,26 +30,143 @@ pub struct Audio {
     #[serde(default)]
     looping: bool,
     #[serde(default)]
+    fade_in: Fade,
+    #[serde(default)]
+    fade_out: Fade,
+    #[serde(default)]
+    crossfade: bool,
+    #[serde(default)]
+    trigger: OptionalPath,
+    #[serde(default)]
+    routing: AudioRouting,
+    #[serde(default)]
     in_volume: SignalId,
+    #[serde(default)]
+    in_pause: SignalId,
+    #[serde(default)]
+    in_seek: SignalId,
+    #[serde(default)]
+    in_ramp: SignalId,
+    #[serde(default)]
+    out_position: SignalId,
+    #[serde(default = "defaults::position_rate")]
+    position_rate: f32,
+    #[serde(skip)]
+    synth: Option<Synth>,
 }
 
 stateful_arc!(Audio {
     duration: Duration,
     looping: bool,
+    fade_out: Duration,
     sink: Arc<Mutex<Option<AudioSink>>>,
     link: Option<(Sender<()>, Receiver<()>)>,
     in_volume: SignalId,
+    in_pause: SignalId,
+    in_seek: SignalId,
+    in_ramp: SignalId,
+    out_position: SignalId,
+    position_period: Duration,
 });
 
+mod defaults {
+    #[inline(always)]
+    pub fn position_rate() -> f32 {
+        10.0
+    }
+}
+
+impl Audio {
+    /// Audio that plays a synthesized sound instead of a file.
+    pub fn synthesized(
+        synth: Synth,
+        volume: Volume,
+        channel: AudioChannel,
+        looping: bool,
+        in_volume: SignalId,
+    ) -> Self {
+        Self {
+            src: PathBuf::new(),
+            volume,
+            channel,
+            looping,
+            fade_in: Fade::Value(0.0),
+            fade_out: Fade::Value(0.0),
+            crossfade: false,
+            trigger: None.into(),
+            routing: AudioRouting::default(),
+            in_volume,
+            in_pause: 0,
+            in_seek: 0,
+            in_ramp: 0,
+            out_position: 0,
+            position_rate: defaults::position_rate(),
+            synth: Some(synth),
+        }
+    }
+
+    #[inline]
+    fn source(&self, channel: AudioChannel) -> ResourceAddr {
+        match self.synth.as_ref() {
+            Some(synth) => ResourceAddr::Synth(synth.clone(), channel),
+            None => ResourceAddr::Audio(self.src.to_owned(), channel),
+        }
+    }
+}
+
 impl Action for Audio {
+    fn init(self) -> Result<Box<dyn Action>>
+    where
+        Self: 'static + Sized,
+    {
+        self.routing.verify()?;
+        self.fade_in.verify()?;
+        self.fade_out.verify()?;
+        if !self.position_rate.is_finite() || self.position_rate <= 0.0 {
+            return Err(eyre!(
+                "Audio `position_rate` should be a positive number of updates per second ({}).",
+                self.position_rate
+            ));
+        }
+        if self.crossfade && self.looping {
+            return Err(eyre!(
+                "Looping audio cannot crossfade into the next action."
+            ));
+        }
+        if !self.routing.is_empty()
+            && self.trigger.as_ref().is_none()
+            && self.channel != AudioChannel::Stereo
+        {
+            return Err(eyre!(
+                "Audio `channel` cannot be used alongside `routing` without a `trigger`."
+            ));
+        }
+
+        Ok(Box::new(self))
+    }
+
     #[inline(always)]
     fn in_signals(&self) -> BTreeSet<SignalId> {
-        BTreeSet::from([self.in_volume])
+        BTreeSet::from([self.in_volume, self.in_pause, self.in_seek, self.in_ramp])
+    }
+
+    #[inline(always)]
+    fn out_signals(&self) -> BTreeSet<SignalId> {
+        BTreeSet::from([self.out_position])
     }
 
     #[inline(always)]
     fn resources(&self, _config: &Config) -> Vec<ResourceAddr> {
-        vec![ResourceAddr::Audio(self.src.to_owned(), self.channel)]
+        if let Some(trigger) = self.trigger.as_ref() {
+            vec![
+                self.source(AudioChannel::Stereo),
+                ResourceAddr::Audio(trigger.to_owned(), AudioChannel::Stereo),
+            ]
+        } else if !self.routing.is_empty() {
+            vec![self.source(AudioChannel::Stereo)]
+        } else {
+            vec![self.source(self.channel)]
+        }
     }
 
     fn stateful(
@@ -60,18 +177,70 @@ impl Action for Audio {
         _sync_writer: &QWriter<SyncSignal>,
         _async_writer: &QWriter<AsyncSignal>,
     ) -> Result<Box<dyn StatefulAction>> {
-        let src = ResourceAddr::Audio(self.src.clone(), self.channel);
-        let src = if let ResourceValue::Audio(src) = res.fetch(&src)? {
+        let fetch = |src: ResourceAddr| -> Result<AudioBuffer> {
+            if let ResourceValue::Audio(src) = res.fetch(&src)? {
+                Ok(src)
+            } else {
+                Err(eyre!("Resource value and address types don't match."))
+            }
+        };
+
+        // The trigger is interlaced into the same buffer, so that it is sample-aligned with audio
+        let src = if let Some(trigger) = self.trigger.as_ref() {
+            fetch(self.source(AudioChannel::Stereo))?
+                .with_trigger(
+                    fetch(ResourceAddr::Audio(
+                        trigger.to_owned(),
+                        AudioChannel::Stereo,
+                    ))?,
+                    self.channel,
+                )
+                .wrap_err("Failed to interlace trigger with audio.")?
+        } else if !self.routing.is_empty() {
+            fetch(self.source(AudioChannel::Stereo))?
+        } else {
+            fetch(self.source(self.channel))?
+        };
+
+        let src = if self.routing.is_empty() {
             src
         } else {
-            return Err(eyre!("Resource value and address types don't match."));
+            // Unused device channels are padded with silence
+            let mut routing = self.routing.clone();
+            if let Some(channels) = config.audio_channels() {
+                if routing.outputs() > channels {
+                    return Err(eyre!(
+                        "Audio routing has {} output channels, but device is configured with {}.",
+                        routing.outputs(),
+                        channels
+                    ));
+                }
+                routing = routing.padded(channels);
+            } else if let Some(channels) = io.audio_channels() {
+                if routing.outputs() > channels {
+                    return Err(eyre!(
+                        "Audio routing has {} output channels, but device only has {} (see `audio_channels`).",
+                        routing.outputs(),
+                        channels
+                    ));
+                }
+            }
+            src.routed(&routing)
+                .wrap_err("Failed to route audio channels.")?
         };
 
         let duration = src.duration();
         let volume = self.volume.or(&config.volume());
         let mut sink = io.audio()?;
 
+        let fade_in = self.fade_in.or(&config.fade_in()).value();
+        let fade_out = self.fade_out.or(&config.fade_out()).value();
+        if self.crossfade && fade_out.is_zero() {
+            return Err(eyre!("Audio `crossfade` requires a positive `fade_out`."));
+        }
+
         sink.set_volume(volume.value())?;
+        sink.set_fades(fade_in, fade_out)?;
         if self.looping {
             sink.repeat(src)?;
         } else {
@@ -88,6 +257,8 @@ impl Action for Audio {
             let sink = sink.clone();
             let time_precision = config.time_precision();
             let looping = self.looping;
+            let crossfade = self.crossfade;
+            let controlled = self.in_pause > 0 || self.in_seek > 0;
             let sleeper = spin_sleeper();
 
             thread::spawn(move || {
@@ -114,9 +285,48 @@ impl Action for Audio {
                     // take longer to finish playing due to IO delay, etc.), leaving what remains
                     // to be played in a serial or parallel mode depending on time_precision conf
                     let target_time = Instant::now() + duration;
-                    sleeper.sleep(target_time - Instant::now());
+                    if controlled {
+                        // playback can be paused or moved around, so follow its position
+                        // instead of the clock
+                        let end = if crossfade {
+                            duration.saturating_sub(fade_out)
+                        } else {
+                            duration
+                        };
+                        let step = Duration::from_millis(1);
+                        loop {
+                            let position = match sink.lock().unwrap().as_ref() {
+                                Some(sink) if !matches!(sink.empty(), Ok(false)) => break,
+                                Some(sink) => sink.position().unwrap_or(end),
+                                None => break,
+                            };
+                            if position >= end {
+                                break;
+                            }
+                            sleeper.sleep((end - position).min(step));
+                        }
+                    } else if crossfade {
+                        // end as soon as the fade-out starts, letting it overlap with whatever
+                        // comes next
+                        sleeper.sleep(
+                            (target_time - fade_out).saturating_duration_since(Instant::now()),
+                        );
+                    } else {
+                        sleeper.sleep(target_time - Instant::now());
+                    }
 
                     match time_precision {
+                        _ if crossfade => {
+                            if let Some(sink) = sink.lock().unwrap().take() {
+                                if let Err(e) = sink.detach() {
+                                    *done.lock().unwrap() = Err(e);
+                                } else {
+                                    *done.lock().unwrap() = Ok(true);
+                                }
+                            } else {
+                                *done.lock().unwrap() = Ok(true);
+                            }
+                        }
                         TimePrecision::Inherit => {
                             *done.lock().unwrap() = Err(eyre!(
                                 "Invalid state at runtime (time_precision=`Inherit`)."
@@ -166,9 +376,15 @@ impl Action for Audio {
             done,
             duration,
             looping: self.looping,
+            fade_out,
             sink,
             link: Some((tx_start, rx_stop)),
             in_volume: self.in_volume,
+            in_pause: self.in_pause,
+            in_seek: self.in_seek,
+            in_ramp: self.in_ramp,
+            out_position: self.out_position,
+            position_period: Duration::from_secs_f32(1.0 / self.position_rate),
         }))
     }
 }
@@ -196,6 +412,27 @@ impl StatefulAction for StatefulAudio {
             .send(())
             .wrap_err("Failed to send start signal to concurrent audio thread.")?;
 
+        if self.out_position > 0 {
+            let sink = self.sink.clone();
+            let period = self.position_period;
+            let out_position = self.out_position;
+            let mut sync_writer = sync_writer.clone();
+            thread::spawn(move || {
+                let sleeper = spin_sleeper();
+                loop {
+                    let position = match sink.lock().unwrap().as_ref().map(|s| s.position()) {
+                        Some(Ok(position)) => position,
+                        _ => break,
+                    };
+                    sync_writer.push(SyncSignal::Emit(
+                        Instant::now(),
+                        vec![(out_position, Value::Float(position.as_secs_f64()))].into(),
+                    ));
+                    sleeper.sleep(period);
+                }
+            });
+        }
+
         if let Ok(true) = *self.done.lock().unwrap() {
             sync_writer.push(SyncSignal::UpdateGraph);
         } else {
@@ -219,19 +456,61 @@ impl StatefulAction for StatefulAudio {
         _async_writer: &mut QWriter<AsyncSignal>,
         state: &State,
     ) -> Result<Signal> {
-        match signal {
-            ActionSignal::StateChanged(_, signal) if signal.contains(&self.in_volume) => {}
+        let changed = match signal {
+            ActionSignal::StateChanged(_, signal) => signal,
             _ => return Ok(Signal::none()),
         };
 
-        if let Some(Value::Float(vol)) = state.get(&self.in_volume) {
-            let vol = vol.clamp(0.0, 1.0) as f32;
-            if let Some(sink) = self.sink.lock().unwrap().as_mut() {
+        let mut sink = self.sink.lock().unwrap();
+        let sink = match sink.as_mut() {
+            Some(sink) => sink,
+            None => return Ok(Signal::none()),
+        };
+
+        if changed.contains(&self.in_volume) {
+            if let Some(Value::Float(vol)) = state.get(&self.in_volume) {
+                let vol = vol.clamp(0.0, 1.0) as f32;
                 sink.set_volume(vol)
                     .wrap_err("Failed to set audio volume to new value.")?;
             }
         }
 
+        if changed.contains(&self.in_ramp) {
+            if let Some(value) = state.get(&self.in_ramp) {
+                let (vol, secs) = match value {
+                    Value::Array(v) if v.len() == 2 => (to_f64(&v[0])?, to_f64(&v[1])?),
+                    _ => {
+                        return Err(eyre!(
+                            "Expected a pair of (volume, seconds) for audio ramp, found {value:?}."
+                        ))
+                    }
+                };
+                let ramp = Duration::from_secs_f64(secs.max(0.0));
+                sink.ramp_volume(vol.clamp(0.0, 1.0) as f32, ramp)
+                    .wrap_err("Failed to ramp audio volume to new value.")?;
+            }
+        }
+
+        if changed.contains(&self.in_pause) {
+            match state.get(&self.in_pause) {
+                Some(Value::Bool(true)) => sink.pause().wrap_err("Failed to pause audio.")?,
+                Some(Value::Bool(false)) => sink.play().wrap_err("Failed to resume audio.")?,
+                Some(value) => {
+                    return Err(eyre!(
+                        "Expected a boolean to pause/resume audio, found {value:?}."
+                    ))
+                }
+                None => {}
+            }
+        }
+
+        if changed.contains(&self.in_seek) {
+            if let Some(value) = state.get(&self.in_seek) {
+                let offset = Duration::from_secs_f64(to_f64(value)?.max(0.0));
+                sink.seek(offset).wrap_err("Failed to seek audio.")?;
+            }
+        }
+
         Ok(Signal::none())
     }
 
@@ -243,7 +522,13 @@ impl StatefulAction for StatefulAudio {
         _state: &State,
     ) -> Result<Signal> {
         if let Some(mut sink) = self.sink.lock().unwrap().take() {
-            sink.stop().wrap_err("Failed to stop audio sink.")?;
+            if self.link.is_none() && !self.fade_out.is_zero() {
+                // let the audio fade out on its own instead of cutting it off with a click
+                sink.release().wrap_err("Failed to fade out audio sink.")?;
+                sink.detach().wrap_err("Failed to detach audio sink.")?;
+            } else {
+                sink.stop().wrap_err("Failed to stop audio sink.")?;
+            }
         }
         Ok(Signal::none())
     }
@@ -256,6 +541,16 @@ impl StatefulAction for StatefulAudio {
     }
 }
 
+fn to_f64(value: &Value) -> Result<f64> {
+    match value {
+        Value::Integer(v) => Ok(*v as f64),
+        Value::Float(v) if v.is_finite() => Ok(*v),
+        _ => Err(eyre!(
+            "Expected a number to control audio, found {value:?}."
+        )),
+    }
+}
+
 impl Drop for StatefulAudio {
     fn drop(&mut self) {
         if let Some(mut sink) = self.sink.lock().unwrap().take() {
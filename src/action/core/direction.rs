@@ -0,0 +1,397 @@
+use crate::action::{Action, Props, StatefulAction, VISUAL};
+use crate::comm::{QWriter, Signal, SignalId};
+use crate::gui::{ACTIVE_BLUE, TEXT_SIZE_BODY};
+use crate::resource::{IoManager, LoggerSignal, OptionalString, ResourceManager};
+use crate::server::{AsyncSignal, Config, State, SyncSignal};
+use eframe::egui;
+use eframe::egui::{
+    Align2, Color32, CursorIcon, FontId, Painter, Pos2, Response, Sense, Shape, Stroke, Vec2,
+};
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use serde_cbor::Value;
+use std::collections::BTreeSet;
+use std::time::Instant;
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Direction {
+    #[serde(default)]
+    mode: Mode,
+    #[serde(default)]
+    range: Range,
+    #[serde(default = "defaults::radius")]
+    radius: f32,
+    #[serde(default = "defaults::group")]
+    group: OptionalString,
+    #[serde(default)]
+    out_angle: SignalId,
+    #[serde(default)]
+    out_slice: SignalId,
+    #[serde(default)]
+    out_rt: SignalId,
+}
+
+stateful!(Direction {
+    mode: Mode,
+    range: Range,
+    radius: f32,
+    group: Option<String>,
+    since: Instant,
+    out_angle: SignalId,
+    out_slice: SignalId,
+    out_rt: SignalId,
+});
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    #[default]
+    Continuous,
+    Quantized(u32),
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Range {
+    Front,
+    #[default]
+    Full,
+}
+
+impl Range {
+    /// Smallest allowed angle (in degrees), counted clockwise from the front.
+    #[inline]
+    fn min(&self) -> f32 {
+        match self {
+            Range::Front => -90.0,
+            Range::Full => -180.0,
+        }
+    }
+
+    /// Span of allowed angles (in degrees).
+    #[inline]
+    fn span(&self) -> f32 {
+        match self {
+            Range::Front => 180.0,
+            Range::Full => 360.0,
+        }
+    }
+}
+
+mod defaults {
+    use crate::resource::OptionalString;
+
+    #[inline(always)]
+    pub fn radius() -> f32 {
+        300.0
+    }
+
+    #[inline(always)]
+    pub fn group() -> OptionalString {
+        Some("direction".to_owned()).into()
+    }
+}
+
+impl Action for Direction {
+    fn init(self) -> Result<Box<dyn Action>>
+    where
+        Self: 'static + Sized,
+    {
+        match self.mode {
+            Mode::Quantized(0) => {
+                return Err(eyre!("Quantized Direction requires at least one slice."));
+            }
+            Mode::Continuous if self.out_slice > 0 => {
+                return Err(eyre!(
+                    "Direction `out_slice` can only be used in `quantized` mode."
+                ));
+            }
+            _ => {}
+        }
+
+        if self.radius <= 0.0 {
+            return Err(eyre!(
+                "Direction `radius` should be positive ({}).",
+                self.radius
+            ));
+        }
+
+        Ok(Box::new(self))
+    }
+
+    #[inline]
+    fn out_signals(&self) -> BTreeSet<SignalId> {
+        BTreeSet::from([self.out_angle, self.out_slice, self.out_rt])
+    }
+
+    fn stateful(
+        &self,
+        _io: &IoManager,
+        _res: &ResourceManager,
+        _config: &Config,
+        _sync_writer: &QWriter<SyncSignal>,
+        _async_writer: &QWriter<AsyncSignal>,
+    ) -> Result<Box<dyn StatefulAction>> {
+        Ok(Box::new(StatefulDirection {
+            done: false,
+            mode: self.mode,
+            range: self.range,
+            radius: self.radius,
+            group: self.group.as_ref().map(|s| s.to_owned()),
+            since: Instant::now(),
+            out_angle: self.out_angle,
+            out_slice: self.out_slice,
+            out_rt: self.out_rt,
+        }))
+    }
+}
+
+/// Point at `radius` from `center` in the direction of `angle` (in degrees, clockwise from the
+/// front, which points up on screen).
+#[inline]
+fn polar(center: Pos2, radius: f32, angle: f32) -> Pos2 {
+    let angle = angle.to_radians();
+    center + radius * Vec2::new(angle.sin(), -angle.cos())
+}
+
+fn arc(center: Pos2, radius: f32, from: f32, to: f32) -> Vec<Pos2> {
+    let steps = ((to - from).abs() / 2.0).ceil().max(1.0) as usize;
+    (0..=steps)
+        .map(|i| polar(center, radius, from + (to - from) * i as f32 / steps as f32))
+        .collect()
+}
+
+impl StatefulDirection {
+    /// Angle (in degrees, clockwise from the front) pointed at by `pos`, if it is allowed.
+    fn angle_of(&self, center: Pos2, pos: Pos2) -> Option<f32> {
+        let d = pos - center;
+        if d.length() < f32::EPSILON {
+            return None;
+        }
+
+        let angle = d.x.atan2(-d.y).to_degrees();
+        let min = self.range.min();
+        if (min..=min + self.range.span()).contains(&angle) {
+            Some(angle)
+        } else {
+            None
+        }
+    }
+
+    /// Index of the slice containing `angle`, counted clockwise from the smallest allowed angle.
+    fn slice_of(&self, angle: f32) -> Option<u32> {
+        match self.mode {
+            Mode::Continuous => None,
+            Mode::Quantized(n) => {
+                let width = self.range.span() / n as f32;
+                Some((((angle - self.range.min()) / width) as u32).min(n - 1))
+            }
+        }
+    }
+
+    fn paint_head(&self, painter: &Painter, center: Pos2) {
+        let stroke = Stroke::new(3.0, Color32::DARK_GRAY);
+        let head = self.radius * 0.25;
+
+        // Nose and ears
+        painter.add(Shape::convex_polygon(
+            vec![
+                polar(center, head * 1.3, 0.0),
+                polar(center, head, 20.0),
+                polar(center, head, -20.0),
+            ],
+            Color32::LIGHT_GRAY,
+            stroke,
+        ));
+        for side in [-90.0, 90.0] {
+            painter.circle(
+                polar(center, head, side),
+                head * 0.2,
+                Color32::LIGHT_GRAY,
+                stroke,
+            );
+        }
+        painter.circle(center, head, Color32::LIGHT_GRAY, stroke);
+
+        let font = FontId::proportional(TEXT_SIZE_BODY * 0.8);
+        let labels = [
+            (0.0, Align2::CENTER_BOTTOM, "Front"),
+            (-90.0, Align2::RIGHT_CENTER, "Left"),
+            (90.0, Align2::LEFT_CENTER, "Right"),
+        ];
+        for (angle, anchor, label) in labels {
+            painter.text(
+                polar(center, self.radius + 20.0, angle),
+                anchor,
+                label,
+                font.clone(),
+                Color32::DARK_GRAY,
+            );
+        }
+    }
+
+    fn paint_range(&self, painter: &Painter, center: Pos2, angle: Option<f32>) {
+        let stroke = Stroke::new(2.0, Color32::GRAY);
+        let highlight = Stroke::new(4.0, Color32::from(ACTIVE_BLUE));
+        let head = self.radius * 0.25;
+        let min = self.range.min();
+        let max = min + self.range.span();
+
+        painter.add(Shape::line(arc(center, self.radius, min, max), stroke));
+
+        match (self.mode, angle) {
+            (Mode::Continuous, Some(angle)) => {
+                painter.line_segment(
+                    [
+                        polar(center, head, angle),
+                        polar(center, self.radius, angle),
+                    ],
+                    highlight,
+                );
+            }
+            (Mode::Quantized(n), angle) => {
+                let width = self.range.span() / n as f32;
+                for i in 0..=n {
+                    let boundary = min + i as f32 * width;
+                    painter.line_segment(
+                        [
+                            polar(center, head, boundary),
+                            polar(center, self.radius, boundary),
+                        ],
+                        stroke,
+                    );
+                }
+
+                if let Some(slice) = angle.and_then(|a| self.slice_of(a)) {
+                    let from = min + slice as f32 * width;
+                    painter.add(Shape::line(
+                        arc(center, self.radius, from, from + width),
+                        Stroke::new(12.0, Color32::from(ACTIVE_BLUE)),
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl StatefulAction for StatefulDirection {
+    impl_stateful!();
+
+    #[inline(always)]
+    fn props(&self) -> Props {
+        VISUAL.into()
+    }
+
+    fn start(
+        &mut self,
+        sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        _state: &State,
+    ) -> Result<Signal> {
+        self.since = Instant::now();
+        if let Some(group) = self.group.as_ref() {
+            async_writer.push(LoggerSignal::Append(
+                group.clone(),
+                ("event".to_owned(), Value::Text("start".to_owned())),
+            ));
+        }
+
+        sync_writer.push(SyncSignal::Repaint);
+        Ok(Signal::none())
+    }
+
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        _state: &State,
+    ) -> Result<Response> {
+        let (rect, response) = ui.allocate_exact_size(ui.available_size(), Sense::click());
+        let center = rect.center();
+
+        let angle = ui
+            .input(|i| i.pointer.hover_pos())
+            .and_then(|pos| self.angle_of(center, pos));
+
+        let painter = ui.painter_at(rect);
+        self.paint_range(&painter, center, angle);
+        self.paint_head(&painter, center);
+
+        if response.clicked() {
+            let time = Instant::now();
+            let angle = response
+                .interact_pointer_pos()
+                .and_then(|pos| self.angle_of(center, pos));
+
+            if let Some(angle) = angle {
+                let slice = self.slice_of(angle);
+                let rt = Value::Float(time.duration_since(self.since).as_secs_f64());
+                let slice = match slice {
+                    Some(s) => Value::Integer(s as i128),
+                    None => Value::Null,
+                };
+
+                if let Some(group) = self.group.as_ref() {
+                    async_writer.push(LoggerSignal::Append(
+                        group.clone(),
+                        (
+                            "response".to_owned(),
+                            Value::Array(vec![
+                                Value::Float(angle as f64),
+                                slice.clone(),
+                                rt.clone(),
+                            ]),
+                        ),
+                    ));
+                }
+
+                let mut news = vec![];
+                if self.out_angle > 0 {
+                    news.push((self.out_angle, Value::Float(angle as f64)));
+                }
+                if self.out_slice > 0 {
+                    news.push((self.out_slice, slice));
+                }
+                if self.out_rt > 0 {
+                    news.push((self.out_rt, rt));
+                }
+
+                self.done = true;
+                sync_writer.push(SyncSignal::Emit(time, news.into()));
+                sync_writer.push(SyncSignal::UpdateGraph);
+            }
+        }
+
+        ui.output_mut(|o| o.cursor_icon = CursorIcon::Crosshair);
+
+        Ok(response)
+    }
+
+    fn stop(
+        &mut self,
+        _sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        _state: &State,
+    ) -> Result<Signal> {
+        if let Some(group) = self.group.as_ref() {
+            async_writer.push(LoggerSignal::Append(
+                group.clone(),
+                ("event".to_owned(), Value::Text("stop".to_owned())),
+            ));
+        }
+        Ok(Signal::none())
+    }
+
+    fn debug(&self) -> Vec<(&str, String)> {
+        <dyn StatefulAction>::debug(self)
+            .into_iter()
+            .chain([
+                ("mode", format!("{:?}", self.mode)),
+                ("range", format!("{:?}", self.range)),
+            ])
+            .collect()
+    }
+}
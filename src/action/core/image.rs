@@ -1,13 +1,14 @@
 use crate::action::{Action, Props, StatefulAction, INFINITE, VISUAL};
-use crate::comm::QWriter;
+use crate::comm::{QWriter, SignalId};
 use crate::resource::{
     Color, IoManager, OptionalFloat, ResourceAddr, ResourceManager, ResourceValue,
 };
-use crate::server::{AsyncSignal, Config, State, SyncSignal};
+use crate::server::{register_onset, AsyncSignal, Config, State, SyncSignal};
 use eframe::egui;
 use eframe::egui::{CentralPanel, Color32, Frame, Response, TextureId, Vec2};
 use eyre::{eyre, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 use std::path::PathBuf;
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -20,6 +21,8 @@ pub struct Image {
     background: Color,
     #[serde(default = "defaults::pad")]
     pad: bool,
+    #[serde(default)]
+    out_onset: SignalId,
 }
 
 stateful!(Image {
@@ -28,6 +31,9 @@ stateful!(Image {
     width: Option<f32>,
     background: Color32,
     pad: bool,
+    name: String,
+    out_onset: SignalId,
+    shown: bool,
 });
 
 mod defaults {
@@ -44,11 +50,17 @@ impl Image {
             width: width.into(),
             background,
             pad,
+            out_onset: 0,
         }
     }
 }
 
 impl Action for Image {
+    #[inline(always)]
+    fn out_signals(&self) -> BTreeSet<SignalId> {
+        BTreeSet::from([self.out_onset])
+    }
+
     #[inline(always)]
     fn resources(&self, _config: &Config) -> Vec<ResourceAddr> {
         vec![ResourceAddr::Image(self.src.to_owned())]
@@ -78,6 +90,9 @@ impl Action for Image {
             width: self.width.as_f32(),
             background: self.background.into(),
             pad: self.pad,
+            name: format!("image:{}", self.src.display()),
+            out_onset: self.out_onset,
+            shown: false,
         }))
     }
 }
@@ -97,6 +112,11 @@ impl StatefulAction for StatefulImage {
         _async_writer: &mut QWriter<AsyncSignal>,
         _state: &State,
     ) -> Result<Response> {
+        if !self.shown {
+            self.shown = true;
+            register_onset(ui, self.name.clone(), self.out_onset);
+        }
+
         let scale = if let Some(width) = self.width {
             width / self.size.x
         } else {
@@ -5,7 +5,7 @@ use crate::resource::{
     parse_text, IoManager, OptionalPath, OptionalString, ResourceAddr, ResourceManager,
     ResourceValue,
 };
-use crate::server::{AsyncSignal, Config, State, SyncSignal};
+use crate::server::{register_onset, AsyncSignal, Config, State, SyncSignal};
 use crate::util::f64_with_precision;
 use eframe::egui;
 use eframe::egui::{CursorIcon, Response, ScrollArea};
@@ -32,6 +32,8 @@ pub struct Instruction {
     #[serde(default = "defaults::persistent")]
     #[serde(rename = "static")]
     persistent: bool,
+    #[serde(default)]
+    out_onset: SignalId,
 }
 
 stateful!(Instruction {
@@ -40,6 +42,9 @@ stateful!(Instruction {
     params: BTreeMap<String, String>,
     persistent: bool,
     in_mapping: BTreeMap<SignalId, String>,
+    name: String,
+    out_onset: SignalId,
+    shown: bool,
 });
 
 mod defaults {
@@ -66,6 +71,11 @@ impl Action for Instruction {
         self.in_mapping.keys().cloned().collect()
     }
 
+    #[inline(always)]
+    fn out_signals(&self) -> BTreeSet<SignalId> {
+        BTreeSet::from([self.out_onset])
+    }
+
     #[inline(always)]
     fn resources(&self, _config: &Config) -> Vec<ResourceAddr> {
         if let Some(src) = self.src.as_ref() {
@@ -115,6 +125,13 @@ impl Action for Instruction {
             params,
             persistent: self.persistent,
             in_mapping: self.in_mapping.clone(),
+            name: if let Some(src) = self.src.as_ref() {
+                format!("instruction:{}", src.display())
+            } else {
+                format!("instruction:{}", self.header)
+            },
+            out_onset: self.out_onset,
+            shown: false,
         }))
     }
 }
@@ -196,6 +213,11 @@ impl StatefulAction for StatefulInstruction {
         _async_writer: &mut QWriter<AsyncSignal>,
         _state: &State,
     ) -> Result<Response> {
+        if !self.shown {
+            self.shown = true;
+            register_onset(ui, self.name.clone(), self.out_onset);
+        }
+
         let mut text = self.text.clone();
 
         for (k, v) in self.params.iter() {
@@ -0,0 +1,195 @@
+use crate::action::{update_with_modifiers, Action, ActionSignal, Props, StatefulAction, INFINITE};
+use crate::comm::{QWriter, Signal, SignalId};
+use crate::resource::{IoManager, Key, LoggerSignal, OptionalString, ResourceManager};
+use crate::server::{AsyncSignal, Config, State, SyncSignal};
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use serde_cbor::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Instant;
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeyHold {
+    #[serde(default = "defaults::group")]
+    group: OptionalString,
+    #[serde(default)]
+    keys: BTreeSet<Key>,
+    #[serde(default)]
+    modifiers: bool,
+    #[serde(default)]
+    out_key: SignalId,
+    #[serde(default)]
+    out_duration: SignalId,
+}
+
+stateful!(KeyHold {
+    group: Option<String>,
+    keys: BTreeSet<Key>,
+    held: BTreeMap<Key, Instant>,
+    modifiers: Option<BTreeSet<Key>>,
+    out_key: SignalId,
+    out_duration: SignalId,
+});
+
+mod defaults {
+    use crate::resource::OptionalString;
+
+    #[inline(always)]
+    pub fn group() -> OptionalString {
+        Some("key_hold".to_owned()).into()
+    }
+}
+
+impl Action for KeyHold {
+    fn out_signals(&self) -> BTreeSet<SignalId> {
+        BTreeSet::from([self.out_key, self.out_duration])
+    }
+
+    fn stateful(
+        &self,
+        _io: &IoManager,
+        _res: &ResourceManager,
+        _config: &Config,
+        _sync_writer: &QWriter<SyncSignal>,
+        _async_writer: &QWriter<AsyncSignal>,
+    ) -> Result<Box<dyn StatefulAction>> {
+        if self.group.as_ref().is_none() && self.out_duration == 0 {
+            return Err(eyre!(
+                "Both `group` and `out_duration` for KeyHold cannot be empty simultaneously."
+            ));
+        }
+
+        Ok(Box::new(StatefulKeyHold {
+            done: false,
+            group: self.group.as_ref().map(|s| s.to_owned()),
+            keys: self.keys.clone(),
+            held: BTreeMap::new(),
+            modifiers: (self.modifiers || self.keys.iter().any(Key::is_modifier))
+                .then(BTreeSet::new),
+            out_key: self.out_key,
+            out_duration: self.out_duration,
+        }))
+    }
+}
+
+impl StatefulKeyHold {
+    /// Handles key presses and releases, with modifier keys as regular keys if opted into.
+    fn handle_keys(
+        &mut self,
+        signal: &ActionSignal,
+        sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+    ) -> Result<Signal> {
+        match signal {
+            ActionSignal::KeyPress(time, keys) => {
+                for k in keys.iter() {
+                    if self.keys.is_empty() || self.keys.contains(k) {
+                        self.held.entry(*k).or_insert(*time);
+                    }
+                }
+            }
+            ActionSignal::KeyRelease(time, keys) => {
+                for k in keys.iter() {
+                    let pressed = match self.held.remove(k) {
+                        Some(t) => t,
+                        None => continue,
+                    };
+
+                    let key = Value::Text(format!("{k:?}"));
+                    let duration = Value::Float(time.duration_since(pressed).as_secs_f64());
+
+                    if let Some(group) = self.group.as_ref() {
+                        async_writer.push(LoggerSignal::Append(
+                            group.clone(),
+                            (
+                                "hold".to_owned(),
+                                Value::Array(vec![key.clone(), duration.clone()]),
+                            ),
+                        ));
+                    }
+
+                    let mut news = vec![];
+                    if self.out_key > 0 {
+                        news.push((self.out_key, key));
+                    }
+                    if self.out_duration > 0 {
+                        news.push((self.out_duration, duration));
+                    }
+                    if !news.is_empty() {
+                        sync_writer.push(SyncSignal::Emit(Instant::now(), news.into()));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Ok(Signal::none())
+    }
+}
+
+impl StatefulAction for StatefulKeyHold {
+    impl_stateful!();
+
+    #[inline(always)]
+    fn props(&self) -> Props {
+        INFINITE.into()
+    }
+
+    fn start(
+        &mut self,
+        _sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        _state: &State,
+    ) -> Result<Signal> {
+        if let Some(group) = self.group.as_ref() {
+            async_writer.push(LoggerSignal::Append(
+                group.clone(),
+                ("event".to_owned(), Value::Text("start".to_owned())),
+            ));
+        }
+
+        Ok(Signal::none())
+    }
+
+    fn update(
+        &mut self,
+        signal: &ActionSignal,
+        sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        _state: &State,
+    ) -> Result<Signal> {
+        update_with_modifiers(
+            self,
+            |a| &mut a.modifiers,
+            signal,
+            |a, signal| a.handle_keys(signal, sync_writer, async_writer),
+        )
+    }
+
+    #[inline]
+    fn stop(
+        &mut self,
+        _sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        _state: &State,
+    ) -> Result<Signal> {
+        if let Some(group) = self.group.as_ref() {
+            async_writer.push(LoggerSignal::Append(
+                group.clone(),
+                ("event".to_owned(), Value::Text("stop".to_owned())),
+            ));
+        }
+        Ok(Signal::none())
+    }
+
+    fn debug(&self) -> Vec<(&str, String)> {
+        <dyn StatefulAction>::debug(self)
+            .into_iter()
+            .chain([
+                ("group", format!("{:?}", self.group)),
+                ("held", format!("{:?}", self.held.keys())),
+            ])
+            .collect()
+    }
+}
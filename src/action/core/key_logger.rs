@@ -1,6 +1,6 @@
-use crate::action::{Action, ActionSignal, Props, StatefulAction, INFINITE};
+use crate::action::{update_with_modifiers, Action, ActionSignal, Props, StatefulAction, INFINITE};
 use crate::comm::{QWriter, Signal, SignalId};
-use crate::resource::{IoManager, LoggerSignal, OptionalString, ResourceManager};
+use crate::resource::{IoManager, Key, LoggerSignal, OptionalString, ResourceManager};
 use crate::server::{AsyncSignal, Config, State, SyncSignal};
 use chrono::Local;
 use eyre::{eyre, Result};
@@ -15,12 +15,18 @@ pub struct KeyLogger {
     #[serde(default = "defaults::group")]
     group: OptionalString,
     #[serde(default)]
+    modifiers: bool,
+    #[serde(default)]
     out_key: SignalId,
+    #[serde(default)]
+    out_release: SignalId,
 }
 
 stateful!(KeyLogger {
     group: Option<String>,
+    modifiers: Option<BTreeSet<Key>>,
     out_key: SignalId,
+    out_release: SignalId,
 });
 
 mod defaults {
@@ -34,7 +40,7 @@ mod defaults {
 
 impl Action for KeyLogger {
     fn out_signals(&self) -> BTreeSet<SignalId> {
-        BTreeSet::from([self.out_key])
+        BTreeSet::from([self.out_key, self.out_release])
     }
 
     fn stateful(
@@ -45,9 +51,9 @@ impl Action for KeyLogger {
         _sync_writer: &QWriter<SyncSignal>,
         _async_writer: &QWriter<AsyncSignal>,
     ) -> Result<Box<dyn StatefulAction>> {
-        if self.group.as_ref().is_none() && self.out_key == 0 {
+        if self.group.as_ref().is_none() && self.out_key == 0 && self.out_release == 0 {
             return Err(eyre!(
-                "Both `group` and `out_key` for KeyLogger cannot be empty simultaneously."
+                "`group`, `out_key` and `out_release` for KeyLogger cannot all be empty."
             ));
         }
 
@@ -56,11 +62,54 @@ impl Action for KeyLogger {
         Ok(Box::new(StatefulKeyLogger {
             done: false,
             group,
+            modifiers: self.modifiers.then(BTreeSet::new),
             out_key: self.out_key,
+            out_release: self.out_release,
         }))
     }
 }
 
+impl StatefulKeyLogger {
+    /// Handles key presses and releases, with modifier keys as regular keys if opted into.
+    fn handle_keys(
+        &mut self,
+        signal: &ActionSignal,
+        sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+    ) -> Result<Signal> {
+        let (name, keys, out_signal) = match signal {
+            ActionSignal::KeyPress(_, keys) => ("key", keys, self.out_key),
+            ActionSignal::KeyRelease(_, keys) => ("release", keys, self.out_release),
+            _ => return Ok(Signal::none()),
+        };
+
+        let entry = (
+            name.to_string(),
+            Value::Array(keys.iter().map(|k| Value::Text(format!("{k:?}"))).collect()),
+        );
+
+        if out_signal > 0 {
+            sync_writer.push(SyncSignal::Emit(
+                Instant::now(),
+                Signal::from(
+                    keys.iter()
+                        .map(|k| (out_signal, Value::Text(format!("{k:?}"))))
+                        .collect::<Vec<_>>(),
+                ),
+            ));
+        }
+
+        if let Some(group) = self.group.as_ref() {
+            async_writer.push(AsyncSignal::Logger(
+                Local::now(),
+                LoggerSignal::Append(group.clone(), entry),
+            ));
+        }
+
+        Ok(Signal::none())
+    }
+}
+
 impl StatefulAction for StatefulKeyLogger {
     impl_stateful!();
 
@@ -92,32 +141,12 @@ impl StatefulAction for StatefulKeyLogger {
         async_writer: &mut QWriter<AsyncSignal>,
         _state: &State,
     ) -> Result<Signal> {
-        if let ActionSignal::KeyPress(_, keys) = signal {
-            let entry = (
-                "key".to_string(),
-                Value::Array(keys.iter().map(|k| Value::Text(format!("{k:?}"))).collect()),
-            );
-
-            if self.out_key > 0 {
-                sync_writer.push(SyncSignal::Emit(
-                    Instant::now(),
-                    Signal::from(
-                        keys.iter()
-                            .map(|k| (self.out_key, Value::Text(format!("{k:?}"))))
-                            .collect::<Vec<_>>(),
-                    ),
-                ));
-            }
-
-            if let Some(group) = self.group.as_ref() {
-                async_writer.push(AsyncSignal::Logger(
-                    Local::now(),
-                    LoggerSignal::Append(group.clone(), entry),
-                ));
-            }
-        }
-
-        Ok(Signal::none())
+        update_with_modifiers(
+            self,
+            |a| &mut a.modifiers,
+            signal,
+            |a, signal| a.handle_keys(signal, sync_writer, async_writer),
+        )
     }
 
     #[inline]
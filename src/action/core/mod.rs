@@ -1,30 +1,36 @@
 // This file is automatically generated by the crate build script.
 // DO NOT MODIFY THIS FILE MANUALLY! CHANGES WILL BE REVERTED.
 
+pub mod at;
 #[cfg(feature = "audio")]
 pub mod audio;
 pub mod branch;
 pub mod clock;
 pub mod counter;
 pub mod delayed;
+pub mod direction;
 pub mod event;
 pub mod fixation;
 pub mod function;
 pub mod horizontal;
 pub mod image;
 pub mod instruction;
+pub mod key_hold;
 pub mod key_logger;
 pub mod logger;
 pub mod merge;
+pub mod mouse_tracker;
 pub mod nil;
 pub mod par;
 pub mod pointer;
 pub mod process;
 pub mod question;
+pub mod rating;
 pub mod reaction;
 pub mod rect;
 pub mod repeat;
 pub mod seq;
+pub mod shapes;
 pub mod stack;
 #[cfg(feature = "stream")]
 pub mod stream;
@@ -32,6 +38,9 @@ pub mod switch;
 pub mod template;
 pub mod timeout;
 pub mod timer;
+#[cfg(feature = "audio")]
+pub mod tone;
+pub mod typing;
 pub mod until;
 pub mod vertical;
 #[cfg(feature = "stream")]
@@ -0,0 +1,274 @@
+use crate::action::{Action, ActionSignal, Props, StatefulAction, VISUAL};
+use crate::comm::{QWriter, Signal, SignalId};
+use crate::resource::{
+    Color, IoManager, LoggerSignal, OptionalFloat, ResourceAddr, ResourceManager,
+};
+use crate::server::{AsyncSignal, Config, State, SyncSignal};
+use eframe::egui::{Color32, CursorIcon, Pos2, Response, Ui};
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use serde_cbor::Value;
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MouseTracker {
+    inner: Box<dyn Action>,
+    #[serde(default)]
+    rate: OptionalFloat,
+    #[serde(default = "defaults::group")]
+    group: String,
+    #[serde(default)]
+    cursor: bool,
+    #[serde(default = "defaults::cursor_color")]
+    cursor_color: Color,
+    #[serde(default = "defaults::cursor_radius")]
+    cursor_radius: f32,
+    #[serde(default)]
+    out_pos: SignalId,
+}
+
+stateful!(MouseTracker {
+    inner: Box<dyn StatefulAction>,
+    period: Option<Duration>,
+    group: String,
+    cursor: Option<(Color32, f32)>,
+    out_pos: SignalId,
+    since: Instant,
+    last_sample: Option<(Instant, Pos2)>,
+});
+
+mod defaults {
+    use crate::resource::Color;
+
+    #[inline(always)]
+    pub fn group() -> String {
+        "mouse_tracker".to_owned()
+    }
+
+    #[inline(always)]
+    pub fn cursor_color() -> Color {
+        Color::White
+    }
+
+    #[inline(always)]
+    pub fn cursor_radius() -> f32 {
+        6.0
+    }
+}
+
+impl Action for MouseTracker {
+    fn init(self) -> Result<Box<dyn Action>>
+    where
+        Self: 'static + Sized,
+    {
+        if let Some(rate) = self.rate.as_f32() {
+            if !rate.is_finite() || rate <= 0.0 {
+                return Err(eyre!(
+                    "MouseTracker `rate` should be a positive number of samples per second ({rate})."
+                ));
+            }
+        }
+        if self.group.is_empty() && self.out_pos == 0 {
+            return Err(eyre!(
+                "Both `group` and `out_pos` for MouseTracker cannot be empty simultaneously."
+            ));
+        }
+        if self.cursor_radius <= 0.0 {
+            return Err(eyre!(
+                "MouseTracker `cursor_radius` should be positive ({}).",
+                self.cursor_radius
+            ));
+        }
+
+        Ok(Box::new(self))
+    }
+
+    #[inline]
+    fn in_signals(&self) -> BTreeSet<SignalId> {
+        self.inner.in_signals()
+    }
+
+    #[inline]
+    fn out_signals(&self) -> BTreeSet<SignalId> {
+        let mut signals = self.inner.out_signals();
+        signals.insert(self.out_pos);
+        signals
+    }
+
+    #[inline]
+    fn resources(&self, config: &Config) -> Vec<ResourceAddr> {
+        self.inner.resources(config)
+    }
+
+    fn stateful(
+        &self,
+        io: &IoManager,
+        res: &ResourceManager,
+        config: &Config,
+        sync_writer: &QWriter<SyncSignal>,
+        async_writer: &QWriter<AsyncSignal>,
+    ) -> Result<Box<dyn StatefulAction>> {
+        Ok(Box::new(StatefulMouseTracker {
+            done: false,
+            inner: self
+                .inner
+                .stateful(io, res, config, sync_writer, async_writer)?,
+            period: self
+                .rate
+                .as_ref()
+                .map(|rate| Duration::from_secs_f64(1.0 / rate)),
+            group: self.group.clone(),
+            cursor: self
+                .cursor
+                .then(|| (self.cursor_color.into(), self.cursor_radius)),
+            out_pos: self.out_pos,
+            since: Instant::now(),
+            last_sample: None,
+        }))
+    }
+}
+
+impl StatefulMouseTracker {
+    /// Logs (with time since start in seconds) and emits a normalized pointer position.
+    fn sample(
+        &mut self,
+        time: Instant,
+        coord: Pos2,
+        sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+    ) {
+        self.last_sample = Some((time, coord));
+
+        if !self.group.is_empty() {
+            async_writer.push(LoggerSignal::Append(
+                self.group.clone(),
+                (
+                    "pos".to_owned(),
+                    Value::Array(vec![
+                        Value::Float(time.duration_since(self.since).as_secs_f64()),
+                        Value::Float(coord.x as f64),
+                        Value::Float(coord.y as f64),
+                    ]),
+                ),
+            ));
+        }
+
+        if self.out_pos > 0 {
+            let coord = Value::Array(vec![
+                Value::Float(coord.x as f64),
+                Value::Float(coord.y as f64),
+            ]);
+            sync_writer.push(SyncSignal::Emit(time, vec![(self.out_pos, coord)].into()));
+        }
+    }
+}
+
+impl StatefulAction for StatefulMouseTracker {
+    impl_stateful!();
+
+    fn props(&self) -> Props {
+        (self.inner.props().bits() | VISUAL).into()
+    }
+
+    fn start(
+        &mut self,
+        sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        state: &State,
+    ) -> Result<Signal> {
+        self.since = Instant::now();
+        self.last_sample = None;
+        if !self.group.is_empty() {
+            async_writer.push(LoggerSignal::Append(
+                self.group.clone(),
+                ("event".to_owned(), Value::Text("start".to_owned())),
+            ));
+        }
+
+        self.inner.start(sync_writer, async_writer, state)
+    }
+
+    fn update(
+        &mut self,
+        signal: &ActionSignal,
+        sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        state: &State,
+    ) -> Result<Signal> {
+        let news = self
+            .inner
+            .update(signal, sync_writer, async_writer, state)?;
+        if self.inner.is_over()? {
+            self.done = true;
+        }
+        Ok(news)
+    }
+
+    fn show(
+        &mut self,
+        ui: &mut Ui,
+        sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        state: &State,
+    ) -> Result<Response> {
+        let area = ui.max_rect();
+        let response = self.inner.show(ui, sync_writer, async_writer, state)?;
+
+        let time = Instant::now();
+        if let Some(pos) = ui.input(|i| i.pointer.hover_pos()) {
+            // Normalized such that the tracked area spans (0, 0) to (1, 1)
+            let coord = ((pos - area.min) / area.size()).to_pos2();
+
+            let due = match (self.period, self.last_sample) {
+                (_, None) => true,
+                (Some(period), Some((last, _))) => time.duration_since(last) >= period,
+                (None, Some((_, last))) => last != coord,
+            };
+
+            if due {
+                self.sample(time, coord, sync_writer, async_writer);
+            }
+
+            if let Some((color, radius)) = self.cursor {
+                ui.output_mut(|o| o.cursor_icon = CursorIcon::None);
+                ui.painter().circle_filled(pos, radius, color);
+            }
+        }
+
+        // Keep repainting at the sampling rate even when no input events arrive
+        if let (Some(period), Some((last, _))) = (self.period, self.last_sample) {
+            ui.ctx()
+                .request_repaint_after(period.saturating_sub(time.duration_since(last)));
+        }
+
+        Ok(response)
+    }
+
+    fn stop(
+        &mut self,
+        sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        state: &State,
+    ) -> Result<Signal> {
+        if !self.group.is_empty() {
+            async_writer.push(LoggerSignal::Append(
+                self.group.clone(),
+                ("event".to_owned(), Value::Text("stop".to_owned())),
+            ));
+        }
+        self.inner.stop(sync_writer, async_writer, state)
+    }
+
+    fn debug(&self) -> Vec<(&str, String)> {
+        <dyn StatefulAction>::debug(self)
+            .into_iter()
+            .chain([
+                ("group", format!("{:?}", self.group)),
+                ("period", format!("{:?}", self.period)),
+                ("last_sample", format!("{:?}", self.last_sample)),
+            ])
+            .collect()
+    }
+}
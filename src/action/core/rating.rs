@@ -0,0 +1,438 @@
+use crate::action::{Action, ActionSignal, Props, StatefulAction, VISUAL};
+use crate::comm::{QWriter, Signal, SignalId};
+use crate::gui::{style_ui, text::body, text::button1, Style, ACTIVE_BLUE, TEXT_SIZE_BODY};
+use crate::resource::{
+    IoManager, Key, LoggerSignal, OptionalFloat, OptionalString, ResourceManager,
+};
+use crate::server::{AsyncSignal, Config, State, SyncSignal};
+use eframe::egui;
+use eframe::egui::{Align2, Button, Color32, CursorIcon, FontId, Response, Sense, Stroke, Vec2};
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use serde_cbor::Value;
+use std::collections::BTreeSet;
+use std::time::Instant;
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rating {
+    #[serde(default)]
+    prompt: String,
+    #[serde(default)]
+    scale: Scale,
+    #[serde(default = "defaults::range")]
+    range: (f32, f32),
+    #[serde(default)]
+    labels: Vec<String>,
+    #[serde(default)]
+    initial: OptionalFloat,
+    #[serde(default)]
+    step: OptionalFloat,
+    #[serde(default = "defaults::confirm")]
+    confirm: Key,
+    #[serde(default = "defaults::width")]
+    width: f32,
+    #[serde(default = "defaults::group")]
+    group: OptionalString,
+    #[serde(default)]
+    out_value: SignalId,
+    #[serde(default)]
+    out_rt: SignalId,
+}
+
+stateful!(Rating {
+    prompt: String,
+    scale: Scale,
+    range: (f32, f32),
+    labels: Vec<String>,
+    step: f32,
+    confirm: Key,
+    width: f32,
+    group: Option<String>,
+    value: Option<f32>,
+    since: Instant,
+    out_value: SignalId,
+    out_rt: SignalId,
+});
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scale {
+    #[default]
+    Vas,
+    Likert(u32),
+}
+
+mod defaults {
+    use crate::resource::{Key, OptionalString};
+
+    #[inline(always)]
+    pub fn range() -> (f32, f32) {
+        (0.0, 100.0)
+    }
+
+    #[inline(always)]
+    pub fn confirm() -> Key {
+        Key::Enter
+    }
+
+    #[inline(always)]
+    pub fn width() -> f32 {
+        1000.0
+    }
+
+    #[inline(always)]
+    pub fn group() -> OptionalString {
+        Some("rating".to_owned()).into()
+    }
+}
+
+impl Action for Rating {
+    fn init(self) -> Result<Box<dyn Action>>
+    where
+        Self: 'static + Sized,
+    {
+        match self.scale {
+            Scale::Vas => {
+                let (lo, hi) = self.range;
+                if !lo.is_finite() || !hi.is_finite() || lo == hi {
+                    return Err(eyre!(
+                        "Rating `range` should consist of two different numbers ({lo}, {hi})."
+                    ));
+                }
+            }
+            Scale::Likert(n) => {
+                if n < 2 {
+                    return Err(eyre!("Likert scale of Rating requires at least 2 points."));
+                }
+            }
+        }
+
+        if let Some(step) = self.step.as_f32() {
+            if !step.is_finite() || step <= 0.0 {
+                return Err(eyre!("Rating `step` should be positive ({step})."));
+            }
+        }
+        if self.width <= 0.0 {
+            return Err(eyre!("Rating `width` should be positive ({}).", self.width));
+        }
+        if self.confirm.is_modifier() {
+            return Err(eyre!(
+                "Rating `confirm` key cannot be a modifier ({:?}).",
+                self.confirm
+            ));
+        }
+
+        Ok(Box::new(self))
+    }
+
+    #[inline]
+    fn out_signals(&self) -> BTreeSet<SignalId> {
+        BTreeSet::from([self.out_value, self.out_rt])
+    }
+
+    fn stateful(
+        &self,
+        _io: &IoManager,
+        _res: &ResourceManager,
+        _config: &Config,
+        _sync_writer: &QWriter<SyncSignal>,
+        _async_writer: &QWriter<AsyncSignal>,
+    ) -> Result<Box<dyn StatefulAction>> {
+        let range = match self.scale {
+            Scale::Vas => self.range,
+            Scale::Likert(n) => (1.0, n as f32),
+        };
+
+        let step = match self.scale {
+            Scale::Vas => self
+                .step
+                .as_f32()
+                .unwrap_or_else(|| (range.1 - range.0).abs() / 100.0),
+            Scale::Likert(_) => 1.0,
+        };
+
+        let mut action = StatefulRating {
+            done: false,
+            prompt: self.prompt.clone(),
+            scale: self.scale,
+            range,
+            labels: self.labels.clone(),
+            step,
+            confirm: self.confirm,
+            width: self.width,
+            group: self.group.as_ref().map(|s| s.to_owned()),
+            value: None,
+            since: Instant::now(),
+            out_value: self.out_value,
+            out_rt: self.out_rt,
+        };
+
+        if let Some(initial) = self.initial.as_f32() {
+            action.value = Some(action.value_at(action.fraction_of(initial)));
+        }
+
+        Ok(Box::new(action))
+    }
+}
+
+impl StatefulRating {
+    /// Position of `value` along the scale, from 0.0 (left end) to 1.0 (right end).
+    fn fraction_of(&self, value: f32) -> f32 {
+        ((value - self.range.0) / (self.range.1 - self.range.0)).clamp(0.0, 1.0)
+    }
+
+    /// Value at a position along the scale, snapped to the nearest point on Likert scales.
+    fn value_at(&self, fraction: f32) -> f32 {
+        let value = self.range.0 + fraction.clamp(0.0, 1.0) * (self.range.1 - self.range.0);
+        match self.scale {
+            Scale::Vas => value,
+            Scale::Likert(_) => value.round(),
+        }
+    }
+
+    fn to_value(&self, value: f32) -> Value {
+        match self.scale {
+            Scale::Vas => Value::Float(value as f64),
+            Scale::Likert(_) => Value::Integer(value as i128),
+        }
+    }
+
+    fn set(&mut self, value: f32, time: Instant, async_writer: &mut QWriter<AsyncSignal>) {
+        if self.value == Some(value) {
+            return;
+        }
+
+        self.value = Some(value);
+        if let Some(group) = self.group.as_ref() {
+            async_writer.push(LoggerSignal::Append(
+                group.clone(),
+                (
+                    "move".to_owned(),
+                    Value::Array(vec![
+                        Value::Float(time.duration_since(self.since).as_secs_f64()),
+                        self.to_value(value),
+                    ]),
+                ),
+            ));
+        }
+    }
+
+    fn submit(&mut self, time: Instant, async_writer: &mut QWriter<AsyncSignal>) -> Signal {
+        let value = match self.value {
+            Some(v) => self.to_value(v),
+            None => return Signal::none(),
+        };
+
+        self.done = true;
+        let rt = Value::Float(time.duration_since(self.since).as_secs_f64());
+        if let Some(group) = self.group.as_ref() {
+            async_writer.push(LoggerSignal::Append(
+                group.clone(),
+                (
+                    "response".to_owned(),
+                    Value::Array(vec![value.clone(), rt.clone()]),
+                ),
+            ));
+        }
+
+        let mut news = vec![];
+        if self.out_value > 0 {
+            news.push((self.out_value, value));
+        }
+        if self.out_rt > 0 {
+            news.push((self.out_rt, rt));
+        }
+        news.into()
+    }
+
+    fn show_scale(&mut self, ui: &mut egui::Ui, async_writer: &mut QWriter<AsyncSignal>) {
+        let stroke = Stroke::new(3.0, Color32::DARK_GRAY);
+        let (rect, response) =
+            ui.allocate_exact_size(Vec2::new(self.width, 60.0), Sense::click_and_drag());
+
+        if response.clicked() || response.dragged() {
+            if let Some(pos) = response.interact_pointer_pos() {
+                let value = self.value_at((pos.x - rect.left()) / rect.width());
+                self.set(value, Instant::now(), async_writer);
+            }
+        }
+
+        let x_at = |fraction: f32| rect.left() + fraction * rect.width();
+        let painter = ui.painter();
+        painter.line_segment([rect.left_center(), rect.right_center()], stroke);
+
+        let ticks = match self.scale {
+            Scale::Vas => 2,
+            Scale::Likert(n) => n as usize,
+        };
+        for i in 0..ticks {
+            let x = x_at(i as f32 / (ticks - 1) as f32);
+            painter.line_segment(
+                [
+                    egui::pos2(x, rect.center().y - 12.0),
+                    egui::pos2(x, rect.center().y + 12.0),
+                ],
+                stroke,
+            );
+        }
+
+        if let Some(value) = self.value {
+            painter.circle_filled(
+                egui::pos2(x_at(self.fraction_of(value)), rect.center().y),
+                14.0,
+                Color32::from(ACTIVE_BLUE),
+            );
+        }
+
+        let (rect, _) =
+            ui.allocate_exact_size(Vec2::new(self.width, TEXT_SIZE_BODY * 1.5), Sense::hover());
+        for (i, label) in self.labels.iter().enumerate() {
+            let fraction = if self.labels.len() > 1 {
+                i as f32 / (self.labels.len() - 1) as f32
+            } else {
+                0.5
+            };
+
+            ui.painter().text(
+                egui::pos2(x_at(fraction), rect.top()),
+                Align2::CENTER_TOP,
+                label,
+                FontId::proportional(TEXT_SIZE_BODY * 0.8),
+                Color32::DARK_GRAY,
+            );
+        }
+    }
+}
+
+impl StatefulAction for StatefulRating {
+    impl_stateful!();
+
+    #[inline(always)]
+    fn props(&self) -> Props {
+        VISUAL.into()
+    }
+
+    fn start(
+        &mut self,
+        sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        _state: &State,
+    ) -> Result<Signal> {
+        self.since = Instant::now();
+        if let Some(group) = self.group.as_ref() {
+            async_writer.push(LoggerSignal::Append(
+                group.clone(),
+                ("event".to_owned(), Value::Text("start".to_owned())),
+            ));
+        }
+
+        sync_writer.push(SyncSignal::Repaint);
+        Ok(Signal::none())
+    }
+
+    fn update(
+        &mut self,
+        signal: &ActionSignal,
+        sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        _state: &State,
+    ) -> Result<Signal> {
+        let (time, keys) = match signal {
+            ActionSignal::KeyPress(time, keys) => (*time, keys),
+            _ => return Ok(Signal::none()),
+        };
+
+        if keys.contains(&self.confirm) {
+            let news = self.submit(time, async_writer);
+            if self.done {
+                return Ok(news);
+            }
+        }
+
+        let direction = if keys.contains(&Key::ArrowLeft) {
+            -1.0
+        } else if keys.contains(&Key::ArrowRight) {
+            1.0
+        } else {
+            return Ok(Signal::none());
+        };
+        let sign = (self.range.1 - self.range.0).signum();
+        let value = match self.value {
+            Some(v) => v + direction * sign * self.step,
+            // Without an initial value, the first arrow key press places the marker at the center
+            None => self.value_at(0.5),
+        };
+        let value = self.value_at(self.fraction_of(value));
+
+        self.set(value, time, async_writer);
+        sync_writer.push(SyncSignal::Repaint);
+        Ok(Signal::none())
+    }
+
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        _state: &State,
+    ) -> Result<Response> {
+        let mut submitted = false;
+        let response = ui
+            .vertical_centered(|ui| {
+                ui.add_space(ui.available_height() / 3.0);
+                if !self.prompt.is_empty() {
+                    ui.label(body(&self.prompt));
+                    ui.add_space(30.0);
+                }
+
+                self.show_scale(ui, async_writer);
+                ui.add_space(30.0);
+
+                ui.scope(|ui| {
+                    style_ui(ui, Style::SubmitButton);
+                    let button = Button::new(button1("Confirm"));
+                    if ui.add_enabled(self.value.is_some(), button).clicked() {
+                        submitted = true;
+                    }
+                });
+            })
+            .response;
+
+        if submitted {
+            let time = Instant::now();
+            let news = self.submit(time, async_writer);
+            sync_writer.push(SyncSignal::Emit(time, news));
+            sync_writer.push(SyncSignal::UpdateGraph);
+        }
+
+        ui.output_mut(|o| o.cursor_icon = CursorIcon::Default);
+
+        Ok(response)
+    }
+
+    fn stop(
+        &mut self,
+        _sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        _state: &State,
+    ) -> Result<Signal> {
+        if let Some(group) = self.group.as_ref() {
+            async_writer.push(LoggerSignal::Append(
+                group.clone(),
+                ("event".to_owned(), Value::Text("stop".to_owned())),
+            ));
+        }
+        Ok(Signal::none())
+    }
+
+    fn debug(&self) -> Vec<(&str, String)> {
+        <dyn StatefulAction>::debug(self)
+            .into_iter()
+            .chain([
+                ("scale", format!("{:?}", self.scale)),
+                ("value", format!("{:?}", self.value)),
+            ])
+            .collect()
+    }
+}
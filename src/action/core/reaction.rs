@@ -1,11 +1,11 @@
-use crate::action::{Action, ActionSignal, Props, StatefulAction, INFINITE};
+use crate::action::{update_with_modifiers, Action, ActionSignal, Props, StatefulAction, INFINITE};
 use crate::comm::{QWriter, Signal, SignalId};
 use crate::resource::{IoManager, Key, LoggerSignal, ResourceManager};
 use crate::server::{AsyncSignal, Config, State, SyncSignal};
 use eyre::{eyre, Error, Result};
 use serde::{Deserialize, Serialize};
 use serde_cbor::Value;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::time::{Duration, Instant};
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -19,6 +19,8 @@ pub struct Reaction {
     #[serde(default = "defaults::tol")]
     tol: f32,
     #[serde(default)]
+    modifiers: bool,
+    #[serde(default)]
     out_rt: SignalId,
     #[serde(default)]
     out_accuracy: SignalId,
@@ -26,6 +28,8 @@ pub struct Reaction {
     out_mean_rt: SignalId,
     #[serde(default)]
     out_recall: SignalId,
+    #[serde(default)]
+    out_hold: SignalId,
 }
 
 stateful!(Reaction {
@@ -38,10 +42,13 @@ stateful!(Reaction {
     reaction_correct: Vec<bool>,
     reaction_times: Vec<f32>,
     reaction_rts: Vec<f32>,
+    held: BTreeMap<Key, Instant>,
+    modifiers: Option<BTreeSet<Key>>,
     out_rt: SignalId,
     out_accuracy: SignalId,
     out_mean_rt: SignalId,
     out_recall: SignalId,
+    out_hold: SignalId,
 });
 
 mod defaults {
@@ -72,6 +79,7 @@ impl Action for Reaction {
             self.out_accuracy,
             self.out_mean_rt,
             self.out_recall,
+            self.out_hold,
         ])
     }
 
@@ -111,10 +119,14 @@ impl Action for Reaction {
             reaction_correct: vec![],
             reaction_times: vec![],
             reaction_rts: vec![],
+            held: BTreeMap::new(),
+            modifiers: (self.modifiers || self.keys.iter().any(Key::is_modifier))
+                .then(BTreeSet::new),
             out_rt: self.out_rt,
             out_accuracy: self.out_accuracy,
             out_recall: self.out_recall,
             out_mean_rt: self.out_mean_rt,
+            out_hold: self.out_hold,
         }))
     }
 }
@@ -147,9 +159,71 @@ impl StatefulAction for StatefulReaction {
         sync_writer: &mut QWriter<SyncSignal>,
         async_writer: &mut QWriter<AsyncSignal>,
         _state: &State,
+    ) -> Result<Signal> {
+        update_with_modifiers(
+            self,
+            |a| &mut a.modifiers,
+            signal,
+            |a, signal| a.handle_keys(signal, sync_writer, async_writer),
+        )
+    }
+
+    #[inline]
+    fn stop(
+        &mut self,
+        _sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        _state: &State,
+    ) -> Result<Signal> {
+        let accuracy = self.accuracy();
+        let mean_rt = self.mean_rt();
+        let recall = self.recall();
+
+        async_writer.push(LoggerSignal::Extend(
+            self.group.clone(),
+            vec![
+                ("event".to_owned(), Value::Text("stop".to_owned())),
+                ("accuracy".to_owned(), Value::Float(accuracy)),
+                ("mean_rt".to_owned(), Value::Float(mean_rt)),
+                ("recall".to_owned(), Value::Float(recall)),
+            ],
+        ));
+
+        let mut news = vec![];
+        if self.out_accuracy > 0 {
+            news.push((self.out_accuracy, Value::Float(accuracy)))
+        }
+        if self.out_mean_rt > 0 {
+            news.push((self.out_mean_rt, Value::Float(mean_rt)))
+        }
+        if self.out_recall > 0 {
+            news.push((self.out_recall, Value::Float(recall)))
+        }
+        Ok(news.into())
+    }
+
+    fn debug(&self) -> Vec<(&str, String)> {
+        <dyn StatefulAction>::debug(self)
+            .into_iter()
+            .chain([("group", format!("{:?}", self.group))])
+            .collect()
+    }
+}
+
+impl StatefulReaction {
+    /// Handles key presses and releases, with modifier keys as regular keys if opted into.
+    fn handle_keys(
+        &mut self,
+        signal: &ActionSignal,
+        sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
     ) -> Result<Signal> {
         let (time, keys) = match signal {
-            ActionSignal::KeyPress(t, k) => (t.duration_since(self.since), k),
+            ActionSignal::KeyPress(t, k) => (t, k),
+            ActionSignal::KeyRelease(t, k) => {
+                self.release(*t, k, sync_writer, async_writer);
+                return Ok(Signal::none());
+            }
             _ => return Ok(Signal::none()),
         };
 
@@ -157,6 +231,13 @@ impl StatefulAction for StatefulReaction {
             return Ok(Signal::none());
         }
 
+        for k in keys.iter() {
+            if self.keys.is_empty() || self.keys.contains(k) {
+                self.held.entry(*k).or_insert(*time);
+            }
+        }
+        let time = time.duration_since(self.since);
+
         self.reaction_times.push(time.as_secs_f32());
 
         let mut correct = false;
@@ -216,49 +297,38 @@ impl StatefulAction for StatefulReaction {
         Ok(Signal::none())
     }
 
-    #[inline]
-    fn stop(
+    /// Logs how long each of the released keys was held since it was pressed.
+    fn release(
         &mut self,
-        _sync_writer: &mut QWriter<SyncSignal>,
+        time: Instant,
+        keys: &BTreeSet<Key>,
+        sync_writer: &mut QWriter<SyncSignal>,
         async_writer: &mut QWriter<AsyncSignal>,
-        _state: &State,
-    ) -> Result<Signal> {
-        let accuracy = self.accuracy();
-        let mean_rt = self.mean_rt();
-        let recall = self.recall();
-
-        async_writer.push(LoggerSignal::Extend(
-            self.group.clone(),
-            vec![
-                ("event".to_owned(), Value::Text("stop".to_owned())),
-                ("accuracy".to_owned(), Value::Float(accuracy)),
-                ("mean_rt".to_owned(), Value::Float(mean_rt)),
-                ("recall".to_owned(), Value::Float(recall)),
-            ],
-        ));
+    ) {
+        for k in keys.iter() {
+            if let Some(pressed) = self.held.remove(k) {
+                let hold = time.duration_since(pressed).as_secs_f64();
+                async_writer.push(LoggerSignal::Append(
+                    self.group.clone(),
+                    (
+                        "release".to_owned(),
+                        Value::Array(vec![
+                            Value::Float(time.duration_since(self.since).as_secs_f64()),
+                            Value::Float(hold),
+                        ]),
+                    ),
+                ));
 
-        let mut news = vec![];
-        if self.out_accuracy > 0 {
-            news.push((self.out_accuracy, Value::Float(accuracy)))
-        }
-        if self.out_mean_rt > 0 {
-            news.push((self.out_mean_rt, Value::Float(mean_rt)))
-        }
-        if self.out_recall > 0 {
-            news.push((self.out_recall, Value::Float(recall)))
+                if self.out_hold > 0 {
+                    sync_writer.push(SyncSignal::Emit(
+                        Instant::now(),
+                        vec![(self.out_hold, Value::Float(hold))].into(),
+                    ));
+                }
+            }
         }
-        Ok(news.into())
-    }
-
-    fn debug(&self) -> Vec<(&str, String)> {
-        <dyn StatefulAction>::debug(self)
-            .into_iter()
-            .chain([("group", format!("{:?}", self.group))])
-            .collect()
     }
-}
 
-impl StatefulReaction {
     #[inline(always)]
     fn accuracy(&self) -> f64 {
         self.reaction_rts.len() as f64 / self.reaction_correct.len() as f64
@@ -1,3 +1,4 @@
+use crate::action::trace::TraceContext;
 use crate::action::{Action, ActionSignal, Props, StatefulAction, INFINITE};
 use crate::comm::{QWriter, Signal, SignalId};
 use crate::resource::{IoManager, OptionalUInt, ResourceAddr, ResourceManager};
@@ -62,6 +63,11 @@ impl Action for Repeat {
             self.prefetch
         };
 
+        // Iterations are built in the order they run, so their trace paths are sequential
+        let inner = self
+            .inner
+            .stateful(io, res, config, sync_writer, async_writer)?;
+
         let mut queue = VecDeque::with_capacity(prefetch as usize);
         for _ in 0..prefetch {
             queue.push_back(
@@ -81,8 +87,12 @@ impl Action for Repeat {
             let config = config.clone();
             let mut sync_writer = sync_writer.clone();
             let async_writer = async_writer.clone();
+            let trace = TraceContext::current();
 
             thread::spawn(move || {
+                // Iterations built here continue the trace path of the ones built above
+                trace.enter();
+
                 let io = match IoManager::new(&config)
                     .wrap_err("Failed to create new IoManager for prefetcher.")
                 {
@@ -126,9 +136,7 @@ impl Action for Repeat {
 
         Ok(Box::new(StatefulRepeat {
             done: false,
-            inner: self
-                .inner
-                .stateful(io, res, config, sync_writer, async_writer)?,
+            inner,
             iters,
             queue,
             link: tx,
@@ -0,0 +1,575 @@
+use crate::action::{Action, ActionSignal, Props, StatefulAction, INFINITE, VISUAL};
+use crate::comm::{QWriter, Signal, SignalId};
+use crate::resource::{Color, IoManager, ResourceManager};
+use crate::server::{register_onset, AsyncSignal, Config, State, SyncSignal};
+use eframe::egui;
+use eframe::egui::{
+    Align2, CentralPanel, Color32, FontId, Frame, Painter, Pos2, Response, Sense, Stroke, Vec2,
+};
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use serde_cbor::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::f32::consts::PI;
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Shapes {
+    shapes: Vec<Primitive>,
+    #[serde(default)]
+    background: Color,
+    #[serde(default)]
+    in_mapping: BTreeMap<SignalId, String>,
+    #[serde(default)]
+    out_onset: SignalId,
+}
+
+stateful!(Shapes {
+    shapes: Vec<Primitive>,
+    background: Color32,
+    in_mapping: BTreeMap<SignalId, (usize, String)>,
+    out_onset: SignalId,
+    shown: bool,
+    changed: BTreeSet<usize>,
+});
+
+/// A shape drawn at task coordinates, i.e., in points relative to the center of the area
+/// occupied by `Shapes`, with `x` increasing to the right and `y` increasing downwards.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "snake_case")]
+pub enum Primitive {
+    Circle {
+        #[serde(default)]
+        id: String,
+        #[serde(default = "defaults::visible")]
+        visible: bool,
+        #[serde(default)]
+        center: (f32, f32),
+        radius: f32,
+        #[serde(default = "defaults::color")]
+        color: Color,
+        #[serde(default)]
+        stroke: f32,
+    },
+    Rect {
+        #[serde(default)]
+        id: String,
+        #[serde(default = "defaults::visible")]
+        visible: bool,
+        #[serde(default)]
+        center: (f32, f32),
+        size: (f32, f32),
+        #[serde(default = "defaults::color")]
+        color: Color,
+        #[serde(default)]
+        stroke: f32,
+    },
+    Polygon {
+        #[serde(default)]
+        id: String,
+        #[serde(default = "defaults::visible")]
+        visible: bool,
+        #[serde(default)]
+        center: (f32, f32),
+        points: Vec<(f32, f32)>,
+        #[serde(default = "defaults::color")]
+        color: Color,
+        #[serde(default)]
+        stroke: f32,
+    },
+    Line {
+        #[serde(default)]
+        id: String,
+        #[serde(default = "defaults::visible")]
+        visible: bool,
+        from: (f32, f32),
+        to: (f32, f32),
+        #[serde(default = "defaults::color")]
+        color: Color,
+        #[serde(default = "defaults::line_stroke")]
+        stroke: f32,
+    },
+    Arrow {
+        #[serde(default)]
+        id: String,
+        #[serde(default = "defaults::visible")]
+        visible: bool,
+        from: (f32, f32),
+        to: (f32, f32),
+        #[serde(default = "defaults::color")]
+        color: Color,
+        #[serde(default = "defaults::line_stroke")]
+        stroke: f32,
+    },
+    Text {
+        #[serde(default)]
+        id: String,
+        #[serde(default = "defaults::visible")]
+        visible: bool,
+        #[serde(default)]
+        center: (f32, f32),
+        text: String,
+        #[serde(default = "defaults::color")]
+        color: Color,
+        #[serde(default = "defaults::text_size")]
+        size: f32,
+    },
+}
+
+mod defaults {
+    use crate::gui::TEXT_SIZE_BODY;
+    use crate::resource::Color;
+
+    #[inline(always)]
+    pub fn visible() -> bool {
+        true
+    }
+
+    #[inline(always)]
+    pub fn color() -> Color {
+        Color::White
+    }
+
+    #[inline(always)]
+    pub fn line_stroke() -> f32 {
+        2.0
+    }
+
+    #[inline(always)]
+    pub fn text_size() -> f32 {
+        TEXT_SIZE_BODY
+    }
+}
+
+impl Action for Shapes {
+    fn init(self) -> Result<Box<dyn Action>>
+    where
+        Self: 'static + Sized,
+    {
+        let mut ids = BTreeSet::new();
+        for shape in self.shapes.iter() {
+            if !shape.id().is_empty() && !ids.insert(shape.id()) {
+                return Err(eyre!("Shape ids should be unique ({}).", shape.id()));
+            }
+            if let Primitive::Polygon { points, .. } = shape {
+                if points.len() < 3 {
+                    return Err(eyre!("Polygons require at least 3 points."));
+                }
+                if !is_convex(points) {
+                    return Err(eyre!("Polygons should be convex ({points:?})."));
+                }
+            }
+        }
+
+        for (_, target) in self.in_mapping.iter() {
+            self.target(target)?;
+        }
+
+        Ok(Box::new(self))
+    }
+
+    #[inline]
+    fn in_signals(&self) -> BTreeSet<SignalId> {
+        self.in_mapping.keys().cloned().collect()
+    }
+
+    #[inline(always)]
+    fn out_signals(&self) -> BTreeSet<SignalId> {
+        BTreeSet::from([self.out_onset])
+    }
+
+    fn stateful(
+        &self,
+        _io: &IoManager,
+        _res: &ResourceManager,
+        _config: &Config,
+        _sync_writer: &QWriter<SyncSignal>,
+        _async_writer: &QWriter<AsyncSignal>,
+    ) -> Result<Box<dyn StatefulAction>> {
+        let mut in_mapping = BTreeMap::new();
+        for (id, target) in self.in_mapping.iter() {
+            in_mapping.insert(*id, self.target(target)?);
+        }
+
+        Ok(Box::new(StatefulShapes {
+            done: false,
+            shapes: self.shapes.clone(),
+            background: self.background.into(),
+            in_mapping,
+            out_onset: self.out_onset,
+            shown: false,
+            changed: BTreeSet::new(),
+        }))
+    }
+}
+
+impl Shapes {
+    /// Resolves an `in_mapping` target of the form `"<id>.<property>"` to the index of the shape
+    /// and the name of the property.
+    fn target(&self, target: &str) -> Result<(usize, String)> {
+        let (id, property) = target.split_once('.').ok_or_else(|| {
+            eyre!("Shapes `in_mapping` targets should be in the form \"id.property\" ({target}).")
+        })?;
+
+        let index = self
+            .shapes
+            .iter()
+            .position(|s| !id.is_empty() && s.id() == id)
+            .ok_or_else(|| eyre!("Undefined shape id `{id}` in `in_mapping`."))?;
+
+        if !self.shapes[index].properties().contains(&property) {
+            return Err(eyre!(
+                "Shape `{id}` has no property `{property}` that can be mapped (expected one of {:?}).",
+                self.shapes[index].properties()
+            ));
+        }
+
+        Ok((index, property.to_owned()))
+    }
+}
+
+impl Primitive {
+    fn id(&self) -> &str {
+        match self {
+            Primitive::Circle { id, .. }
+            | Primitive::Rect { id, .. }
+            | Primitive::Polygon { id, .. }
+            | Primitive::Line { id, .. }
+            | Primitive::Arrow { id, .. }
+            | Primitive::Text { id, .. } => id,
+        }
+    }
+
+    /// Properties that can be set through `in_mapping`.
+    fn properties(&self) -> &[&str] {
+        match self {
+            Primitive::Circle { .. } => &["visible", "center", "radius", "color", "stroke"],
+            Primitive::Rect { .. } => &["visible", "center", "size", "color", "stroke"],
+            Primitive::Polygon { .. } => &["visible", "center", "color", "stroke"],
+            Primitive::Line { .. } | Primitive::Arrow { .. } => {
+                &["visible", "from", "to", "color", "stroke"]
+            }
+            Primitive::Text { .. } => &["visible", "center", "text", "color", "size"],
+        }
+    }
+
+    fn set(&mut self, property: &str, value: &Value) -> Result<()> {
+        match (self, property) {
+            (
+                Primitive::Circle { visible, .. }
+                | Primitive::Rect { visible, .. }
+                | Primitive::Polygon { visible, .. }
+                | Primitive::Line { visible, .. }
+                | Primitive::Arrow { visible, .. }
+                | Primitive::Text { visible, .. },
+                "visible",
+            ) => *visible = to_bool(value)?,
+            (
+                Primitive::Circle { color, .. }
+                | Primitive::Rect { color, .. }
+                | Primitive::Polygon { color, .. }
+                | Primitive::Line { color, .. }
+                | Primitive::Arrow { color, .. }
+                | Primitive::Text { color, .. },
+                "color",
+            ) => *color = to_color(value)?,
+            (
+                Primitive::Circle { center, .. }
+                | Primitive::Rect { center, .. }
+                | Primitive::Polygon { center, .. }
+                | Primitive::Text { center, .. },
+                "center",
+            ) => *center = to_point(value)?,
+            (
+                Primitive::Circle { stroke, .. }
+                | Primitive::Rect { stroke, .. }
+                | Primitive::Polygon { stroke, .. }
+                | Primitive::Line { stroke, .. }
+                | Primitive::Arrow { stroke, .. },
+                "stroke",
+            ) => *stroke = to_f32(value)?,
+            (Primitive::Line { from, .. } | Primitive::Arrow { from, .. }, "from") => {
+                *from = to_point(value)?
+            }
+            (Primitive::Line { to, .. } | Primitive::Arrow { to, .. }, "to") => {
+                *to = to_point(value)?
+            }
+            (Primitive::Circle { radius, .. }, "radius") => *radius = to_f32(value)?,
+            (Primitive::Rect { size, .. }, "size") => *size = to_point(value)?,
+            (Primitive::Text { size, .. }, "size") => *size = to_f32(value)?,
+            (Primitive::Text { text, .. }, "text") => *text = to_text(value),
+            (_, property) => return Err(eyre!("Invalid shape property `{property}`.")),
+        }
+
+        Ok(())
+    }
+
+    fn paint(&self, painter: &Painter, origin: Pos2) {
+        let at = |p: &(f32, f32)| origin + Vec2::from(*p);
+        let stroke_of = |width: &f32, color: &Color| Stroke::new(*width, Color32::from(color));
+
+        match self {
+            Primitive::Circle {
+                visible: true,
+                center,
+                radius,
+                color,
+                stroke,
+                ..
+            } => {
+                if *stroke > 0.0 {
+                    painter.circle_stroke(at(center), *radius, stroke_of(stroke, color));
+                } else {
+                    painter.circle_filled(at(center), *radius, color);
+                }
+            }
+            Primitive::Rect {
+                visible: true,
+                center,
+                size,
+                color,
+                stroke,
+                ..
+            } => {
+                let rect = egui::Rect::from_center_size(at(center), Vec2::from(*size));
+                if *stroke > 0.0 {
+                    painter.rect_stroke(rect, 0.0, stroke_of(stroke, color));
+                } else {
+                    painter.rect_filled(rect, 0.0, color);
+                }
+            }
+            Primitive::Polygon {
+                visible: true,
+                center,
+                points,
+                color,
+                stroke,
+                ..
+            } => {
+                let center = Vec2::from(*center);
+                let points = points.iter().map(|p| at(p) + center).collect();
+                painter.add(if *stroke > 0.0 {
+                    egui::Shape::closed_line(points, stroke_of(stroke, color))
+                } else {
+                    egui::Shape::convex_polygon(points, color, Stroke::NONE)
+                });
+            }
+            Primitive::Line {
+                visible: true,
+                from,
+                to,
+                color,
+                stroke,
+                ..
+            } => {
+                painter.line_segment([at(from), at(to)], stroke_of(stroke, color));
+            }
+            Primitive::Arrow {
+                visible: true,
+                from,
+                to,
+                color,
+                stroke,
+                ..
+            } => {
+                painter.arrow(at(from), at(to) - at(from), stroke_of(stroke, color));
+            }
+            Primitive::Text {
+                visible: true,
+                center,
+                text,
+                color,
+                size,
+                ..
+            } => {
+                painter.text(
+                    at(center),
+                    Align2::CENTER_CENTER,
+                    text,
+                    FontId::proportional(*size),
+                    color.into(),
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether the points (in either winding order) form a convex polygon that turns around only
+/// once, since egui can only fill convex polygons.
+fn is_convex(points: &[(f32, f32)]) -> bool {
+    let n = points.len();
+    let mut sign = 0.0_f32;
+    let mut turning = 0.0_f32;
+    for i in 0..n {
+        let (a, b, c) = (points[i], points[(i + 1) % n], points[(i + 2) % n]);
+        let (u, v) = ((b.0 - a.0, b.1 - a.1), (c.0 - b.0, c.1 - b.1));
+        let cross = u.0 * v.1 - u.1 * v.0;
+        if cross != 0.0 {
+            if sign * cross < 0.0 {
+                return false;
+            }
+            sign = cross.signum();
+        }
+        turning += cross.atan2(u.0 * v.0 + u.1 * v.1);
+    }
+
+    (turning.abs() - 2.0 * PI).abs() < 1e-3
+}
+
+fn to_bool(value: &Value) -> Result<bool> {
+    match value {
+        Value::Bool(v) => Ok(*v),
+        Value::Integer(v) => Ok(*v != 0),
+        _ => Err(eyre!("Expected a boolean shape property, found {value:?}.")),
+    }
+}
+
+fn to_f32(value: &Value) -> Result<f32> {
+    match value {
+        Value::Integer(v) => Ok(*v as f32),
+        Value::Float(v) => Ok(*v as f32),
+        _ => Err(eyre!("Expected a numeric shape property, found {value:?}.")),
+    }
+}
+
+fn to_point(value: &Value) -> Result<(f32, f32)> {
+    match value {
+        Value::Array(v) if v.len() == 2 => Ok((to_f32(&v[0])?, to_f32(&v[1])?)),
+        _ => Err(eyre!(
+            "Expected a pair of numbers as shape property, found {value:?}."
+        )),
+    }
+}
+
+fn to_text(value: &Value) -> String {
+    match value {
+        Value::Text(v) => v.clone(),
+        Value::Bool(v) => v.to_string(),
+        Value::Integer(v) => v.to_string(),
+        Value::Float(v) => v.to_string(),
+        Value::Null => "".to_owned(),
+        _ => "<INVALID>".to_owned(),
+    }
+}
+
+/// Colors are given either by name (e.g., `"red"`) or as an array of RGB(A) components.
+fn to_color(value: &Value) -> Result<Color> {
+    let component = |v: &Value| match v {
+        Value::Integer(v) if (0..=255).contains(v) => Ok(*v as u8),
+        _ => Err(eyre!("Invalid color component in shape property ({v:?}).")),
+    };
+
+    match value {
+        Value::Array(v) if v.len() == 3 => Ok(Color::Rgb(
+            component(&v[0])?,
+            component(&v[1])?,
+            component(&v[2])?,
+        )),
+        Value::Array(v) if v.len() == 4 => Ok(Color::Rgba(
+            component(&v[0])?,
+            component(&v[1])?,
+            component(&v[2])?,
+            component(&v[3])?,
+        )),
+        _ => serde_cbor::value::from_value(value.clone())
+            .map_err(|e| eyre!("Invalid color shape property ({value:?}): {e}")),
+    }
+}
+
+impl StatefulShapes {
+    fn set_from_state(&mut self, id: &SignalId, state: &State) -> Result<()> {
+        if let (Some((index, property)), Some(value)) = (self.in_mapping.get(id), state.get(id)) {
+            self.shapes[*index].set(property, value)?;
+            self.changed.insert(*index);
+        }
+        Ok(())
+    }
+}
+
+impl StatefulAction for StatefulShapes {
+    impl_stateful!();
+
+    #[inline(always)]
+    fn props(&self) -> Props {
+        (INFINITE | VISUAL).into()
+    }
+
+    fn start(
+        &mut self,
+        sync_writer: &mut QWriter<SyncSignal>,
+        _async_writer: &mut QWriter<AsyncSignal>,
+        state: &State,
+    ) -> Result<Signal> {
+        let ids: Vec<_> = self.in_mapping.keys().cloned().collect();
+        for id in ids.iter() {
+            self.set_from_state(id, state)?;
+        }
+        self.changed.clear();
+
+        sync_writer.push(SyncSignal::Repaint);
+        Ok(Signal::none())
+    }
+
+    fn update(
+        &mut self,
+        signal: &ActionSignal,
+        sync_writer: &mut QWriter<SyncSignal>,
+        _async_writer: &mut QWriter<AsyncSignal>,
+        state: &State,
+    ) -> Result<Signal> {
+        if let ActionSignal::StateChanged(_, signal) = signal {
+            for id in signal {
+                self.set_from_state(id, state)?;
+            }
+        }
+
+        if !self.changed.is_empty() {
+            sync_writer.push(SyncSignal::Repaint);
+        }
+        Ok(Signal::none())
+    }
+
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        _sync_writer: &mut QWriter<SyncSignal>,
+        _async_writer: &mut QWriter<AsyncSignal>,
+        _state: &State,
+    ) -> Result<Response> {
+        // Changes to mapped properties are registered as onsets of the changed shapes
+        if !self.shown {
+            self.shown = true;
+            register_onset(ui, "shapes".to_owned(), self.out_onset);
+        }
+        for i in std::mem::take(&mut self.changed) {
+            let name = match self.shapes[i].id() {
+                "" => format!("shapes:{i}"),
+                id => format!("shapes:{id}"),
+            };
+            register_onset(ui, name, self.out_onset);
+        }
+
+        let response = CentralPanel::default()
+            .frame(Frame::default().fill(self.background))
+            .show_inside(ui, |ui| {
+                let (rect, response) = ui.allocate_exact_size(ui.available_size(), Sense::hover());
+                let painter = ui.painter_at(rect);
+                for shape in self.shapes.iter() {
+                    shape.paint(&painter, rect.center());
+                }
+                response
+            })
+            .inner;
+
+        Ok(response)
+    }
+
+    fn debug(&self) -> Vec<(&str, String)> {
+        <dyn StatefulAction>::debug(self)
+            .into_iter()
+            .chain([("shapes", format!("{:?}", self.shapes.len()))])
+            .collect()
+    }
+}
@@ -1,17 +1,19 @@
 //@ stream
 
-use crate::action::{Action, Props, StatefulAction, DEFAULT, INFINITE, VISUAL};
-use crate::comm::{QWriter, Signal};
+use crate::action::{Action, ActionSignal, Props, StatefulAction, DEFAULT, INFINITE, VISUAL};
+use crate::comm::{QWriter, Signal, SignalId};
 use crate::resource::{
     AudioChannel, Color, IoManager, OptionalFloat, ResourceAddr, ResourceManager, ResourceValue,
     StreamMode, Volume,
 };
-use crate::server::{AsyncSignal, Config, State, SyncSignal};
+use crate::server::{register_onset, AsyncSignal, Config, State, SyncSignal};
 use crate::util::spin_sleeper;
 use eframe::egui;
 use eframe::egui::{CentralPanel, Color32, Frame, Response, TextureId, Vec2};
 use eyre::{eyre, Context, Result};
 use serde::{Deserialize, Serialize};
+use serde_cbor::Value;
+use std::collections::BTreeSet;
 use std::path::PathBuf;
 use std::sync::mpsc::{Receiver, Sender, TryRecvError};
 use std::sync::{mpsc, Arc, Mutex};
@@ -35,6 +37,12 @@ pub struct Stream {
     background: Color,
     #[serde(default = "defaults::pad")]
     pad: bool,
+    #[serde(default)]
+    in_volume: SignalId,
+    #[serde(default)]
+    in_mute: SignalId,
+    #[serde(default)]
+    out_onset: SignalId,
 }
 
 stateful_arc!(Stream {
@@ -44,11 +52,23 @@ stateful_arc!(Stream {
     looping: bool,
     link_start: Sender<()>,
     link_stop: Option<Receiver<()>>,
+    link_control: Sender<Control>,
     join_handle: Option<JoinHandle<Result<()>>>,
     background: Color32,
     pad: bool,
+    name: String,
+    in_volume: SignalId,
+    in_mute: SignalId,
+    out_onset: SignalId,
+    shown: bool,
 });
 
+/// Changes to the audio of a playing stream.
+enum Control {
+    Volume(f64),
+    Muted(bool),
+}
+
 mod defaults {
     pub fn pad() -> bool {
         true
@@ -69,6 +89,16 @@ impl Action for Stream {
         Ok(Box::new(self))
     }
 
+    #[inline(always)]
+    fn in_signals(&self) -> BTreeSet<SignalId> {
+        BTreeSet::from([self.in_volume, self.in_mute])
+    }
+
+    #[inline(always)]
+    fn out_signals(&self) -> BTreeSet<SignalId> {
+        BTreeSet::from([self.out_onset])
+    }
+
     #[inline(always)]
     fn resources(&self, _config: &Config) -> Vec<ResourceAddr> {
         vec![ResourceAddr::Stream(self.src.clone())]
@@ -76,7 +106,7 @@ impl Action for Stream {
 
     fn stateful(
         &self,
-        _io: &IoManager,
+        io: &IoManager,
         res: &ResourceManager,
         config: &Config,
         _sync_writer: &QWriter<SyncSignal>,
@@ -91,7 +121,8 @@ impl Action for Stream {
 
         let frame = Arc::new(Mutex::new(None));
         let volume = self.volume.or(&config.volume()).value();
-        let mut stream = stream.cloned(frame.clone(), StreamMode::Normal(self.channel), volume)?;
+        let mut stream =
+            stream.cloned(frame.clone(), StreamMode::Normal(self.channel), volume, io)?;
 
         if !stream.has_video() && self.width.as_ref().is_some() {
             return Err(eyre!(
@@ -110,6 +141,7 @@ impl Action for Stream {
         let done = Arc::new(Mutex::new(Ok(stream.eos())));
         let (tx_start, rx_start) = mpsc::channel();
         let (tx_stop, rx_stop) = mpsc::channel();
+        let (tx_control, rx_control) = mpsc::channel();
         let looping = self.looping;
 
         let done_clone = done.clone();
@@ -126,6 +158,13 @@ impl Action for Stream {
                     break;
                 }
 
+                while let Ok(control) = rx_control.try_recv() {
+                    match control {
+                        Control::Volume(volume) => stream.set_volume(volume)?,
+                        Control::Muted(muted) => stream.set_muted(muted)?,
+                    }
+                }
+
                 sleeper.sleep(period);
                 let mut done = done_clone.lock().unwrap();
                 match (stream.eos(), stream.process_bus(looping)) {
@@ -151,9 +190,15 @@ impl Action for Stream {
             looping,
             link_start: tx_start,
             link_stop: Some(rx_stop),
+            link_control: tx_control,
             join_handle: Some(join_handle),
             background: self.background.into(),
             pad: self.pad,
+            name: format!("stream:{}", self.src.display()),
+            in_volume: self.in_volume,
+            in_mute: self.in_mute,
+            out_onset: self.out_onset,
+            shown: false,
         }))
     }
 }
@@ -216,6 +261,41 @@ impl StatefulAction for StatefulStream {
         Ok(Signal::none())
     }
 
+    fn update(
+        &mut self,
+        signal: &ActionSignal,
+        _sync_writer: &mut QWriter<SyncSignal>,
+        _async_writer: &mut QWriter<AsyncSignal>,
+        state: &State,
+    ) -> Result<Signal> {
+        let changed = match signal {
+            ActionSignal::StateChanged(_, signal) => signal,
+            _ => return Ok(Signal::none()),
+        };
+
+        if changed.contains(&self.in_volume) {
+            if let Some(Value::Float(vol)) = state.get(&self.in_volume) {
+                let _ = self.link_control.send(Control::Volume(vol.clamp(0.0, 1.0)));
+            }
+        }
+
+        if changed.contains(&self.in_mute) {
+            match state.get(&self.in_mute) {
+                Some(Value::Bool(muted)) => {
+                    let _ = self.link_control.send(Control::Muted(*muted));
+                }
+                Some(value) => {
+                    return Err(eyre!(
+                        "Expected a boolean to mute/unmute stream, found {value:?}."
+                    ))
+                }
+                None => {}
+            }
+        }
+
+        Ok(Signal::none())
+    }
+
     fn show(
         &mut self,
         ui: &mut egui::Ui,
@@ -223,6 +303,11 @@ impl StatefulAction for StatefulStream {
         _async_writer: &mut QWriter<AsyncSignal>,
         _state: &State,
     ) -> Result<Response> {
+        if !self.shown {
+            self.shown = true;
+            register_onset(ui, self.name.clone(), self.out_onset);
+        }
+
         let (texture, size) = self
             .frame
             .lock()
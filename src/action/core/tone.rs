@@ -0,0 +1,108 @@
+//@ audio
+
+use crate::action::audio::Audio;
+use crate::action::{Action, StatefulAction};
+use crate::comm::{QWriter, SignalId};
+use crate::resource::{
+    AudioChannel, IoManager, ResourceAddr, ResourceManager, Synth, Volume, Waveform,
+};
+use crate::server::{AsyncSignal, Config, SyncSignal};
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tone {
+    wave: Waveform,
+    duration: f32,
+    #[serde(default = "defaults::level")]
+    level: f32,
+    #[serde(default = "defaults::ramp")]
+    ramp: f32,
+    #[serde(default = "defaults::sample_rate")]
+    sample_rate: u32,
+    #[serde(default)]
+    seed: u64,
+    #[serde(default)]
+    volume: Volume,
+    #[serde(default)]
+    channel: AudioChannel,
+    #[serde(default)]
+    looping: bool,
+    #[serde(default)]
+    in_volume: SignalId,
+}
+
+mod defaults {
+    #[inline(always)]
+    pub fn level() -> f32 {
+        -20.0
+    }
+
+    #[inline(always)]
+    pub fn ramp() -> f32 {
+        0.005
+    }
+
+    #[inline(always)]
+    pub fn sample_rate() -> u32 {
+        48000
+    }
+}
+
+impl Action for Tone {
+    fn init(self) -> Result<Box<dyn Action>>
+    where
+        Self: 'static + Sized,
+    {
+        Synth::from(&self).verify()?;
+        Ok(Box::new(self))
+    }
+
+    #[inline(always)]
+    fn in_signals(&self) -> BTreeSet<SignalId> {
+        BTreeSet::from([self.in_volume])
+    }
+
+    #[inline]
+    fn resources(&self, config: &Config) -> Vec<ResourceAddr> {
+        Audio::from(self).resources(config)
+    }
+
+    fn stateful(
+        &self,
+        io: &IoManager,
+        res: &ResourceManager,
+        config: &Config,
+        sync_writer: &QWriter<SyncSignal>,
+        async_writer: &QWriter<AsyncSignal>,
+    ) -> Result<Box<dyn StatefulAction>> {
+        Audio::from(self).stateful(io, res, config, sync_writer, async_writer)
+    }
+}
+
+impl From<&Tone> for Synth {
+    fn from(tone: &Tone) -> Self {
+        Self {
+            wave: tone.wave.clone(),
+            duration: tone.duration,
+            level: tone.level,
+            ramp: tone.ramp,
+            sample_rate: tone.sample_rate,
+            seed: tone.seed,
+        }
+    }
+}
+
+impl From<&Tone> for Audio {
+    fn from(tone: &Tone) -> Self {
+        Self::synthesized(
+            tone.into(),
+            tone.volume,
+            tone.channel,
+            tone.looping,
+            tone.in_volume,
+        )
+    }
+}
@@ -0,0 +1,236 @@
+use crate::action::{Action, ActionSignal, Props, StatefulAction, VISUAL};
+use crate::comm::{QWriter, Signal, SignalId};
+use crate::gui::text::{body, heading};
+use crate::resource::{IoManager, Key, LoggerSignal, ResourceManager};
+use crate::server::{AsyncSignal, Config, State, SyncSignal};
+use eframe::egui;
+use eframe::egui::{CentralPanel, Layout, Response};
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use serde_cbor::Value;
+use std::collections::BTreeSet;
+use std::time::Instant;
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Typing {
+    #[serde(default)]
+    prompt: String,
+    #[serde(default = "defaults::echo")]
+    echo: bool,
+    #[serde(default = "defaults::submit")]
+    submit: Key,
+    #[serde(default = "defaults::group")]
+    group: String,
+    #[serde(default)]
+    out_text: SignalId,
+    #[serde(default)]
+    out_duration: SignalId,
+}
+
+stateful!(Typing {
+    prompt: String,
+    echo: bool,
+    submit: Key,
+    group: String,
+    text: String,
+    since: Instant,
+    out_text: SignalId,
+    out_duration: SignalId,
+});
+
+mod defaults {
+    use crate::resource::Key;
+
+    #[inline(always)]
+    pub fn echo() -> bool {
+        true
+    }
+
+    #[inline(always)]
+    pub fn submit() -> Key {
+        Key::Enter
+    }
+
+    #[inline(always)]
+    pub fn group() -> String {
+        "typing".to_owned()
+    }
+}
+
+impl Action for Typing {
+    fn init(self) -> Result<Box<dyn Action>>
+    where
+        Self: 'static + Sized,
+    {
+        if self.group.is_empty() {
+            return Err(eyre!("Typing `group` cannot be an empty string"));
+        }
+        if self.submit == Key::Backspace || self.submit.is_modifier() {
+            return Err(eyre!(
+                "Typing `submit` key cannot be a modifier or backspace ({:?}).",
+                self.submit
+            ));
+        }
+
+        Ok(Box::new(self))
+    }
+
+    #[inline]
+    fn out_signals(&self) -> BTreeSet<SignalId> {
+        BTreeSet::from([self.out_text, self.out_duration])
+    }
+
+    fn stateful(
+        &self,
+        _io: &IoManager,
+        _res: &ResourceManager,
+        _config: &Config,
+        _sync_writer: &QWriter<SyncSignal>,
+        _async_writer: &QWriter<AsyncSignal>,
+    ) -> Result<Box<dyn StatefulAction>> {
+        Ok(Box::new(StatefulTyping {
+            done: false,
+            prompt: self.prompt.clone(),
+            echo: self.echo,
+            submit: self.submit,
+            group: self.group.clone(),
+            text: String::new(),
+            since: Instant::now(),
+            out_text: self.out_text,
+            out_duration: self.out_duration,
+        }))
+    }
+}
+
+impl StatefulAction for StatefulTyping {
+    impl_stateful!();
+
+    #[inline(always)]
+    fn props(&self) -> Props {
+        VISUAL.into()
+    }
+
+    fn start(
+        &mut self,
+        sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        _state: &State,
+    ) -> Result<Signal> {
+        self.since = Instant::now();
+        async_writer.push(LoggerSignal::Append(
+            self.group.clone(),
+            ("event".to_owned(), Value::Text("start".to_owned())),
+        ));
+
+        sync_writer.push(SyncSignal::Repaint);
+        Ok(Signal::none())
+    }
+
+    fn update(
+        &mut self,
+        signal: &ActionSignal,
+        sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        _state: &State,
+    ) -> Result<Signal> {
+        // Each keystroke is logged with its time (in seconds) since the action started
+        let entry = match signal {
+            ActionSignal::Text(time, text) => {
+                self.text.push_str(text);
+                (
+                    "keystroke".to_owned(),
+                    Value::Array(vec![
+                        Value::Float(time.duration_since(self.since).as_secs_f64()),
+                        Value::Text(text.clone()),
+                    ]),
+                )
+            }
+            ActionSignal::KeyPress(time, keys) if keys.contains(&self.submit) => {
+                self.done = true;
+                let duration = time.duration_since(self.since).as_secs_f64();
+                async_writer.push(LoggerSignal::Append(
+                    self.group.clone(),
+                    (
+                        "submit".to_owned(),
+                        Value::Array(vec![Value::Float(duration), Value::Text(self.text.clone())]),
+                    ),
+                ));
+
+                let mut news = vec![];
+                if self.out_text > 0 {
+                    news.push((self.out_text, Value::Text(self.text.clone())));
+                }
+                if self.out_duration > 0 {
+                    news.push((self.out_duration, Value::Float(duration)));
+                }
+                return Ok(news.into());
+            }
+            ActionSignal::KeyPress(time, keys) if keys.contains(&Key::Backspace) => {
+                self.text.pop();
+                (
+                    "backspace".to_owned(),
+                    Value::Array(vec![Value::Float(
+                        time.duration_since(self.since).as_secs_f64(),
+                    )]),
+                )
+            }
+            _ => return Ok(Signal::none()),
+        };
+
+        async_writer.push(LoggerSignal::Append(self.group.clone(), entry));
+        if self.echo {
+            sync_writer.push(SyncSignal::Repaint);
+        }
+        Ok(Signal::none())
+    }
+
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        _sync_writer: &mut QWriter<SyncSignal>,
+        _async_writer: &mut QWriter<AsyncSignal>,
+        _state: &State,
+    ) -> Result<Response> {
+        let response = CentralPanel::default()
+            .show_inside(ui, |ui| {
+                ui.with_layout(Layout::top_down(egui::Align::Center), |ui| {
+                    ui.add_space(ui.available_height() / 3.0);
+                    if !self.prompt.is_empty() {
+                        ui.label(heading(&self.prompt));
+                        ui.add_space(40.0);
+                    }
+                    if self.echo {
+                        ui.label(body(format!("{}_", self.text)));
+                    }
+                })
+                .response
+            })
+            .inner;
+
+        Ok(response)
+    }
+
+    fn stop(
+        &mut self,
+        _sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        _state: &State,
+    ) -> Result<Signal> {
+        async_writer.push(LoggerSignal::Append(
+            self.group.clone(),
+            ("event".to_owned(), Value::Text("stop".to_owned())),
+        ));
+        Ok(Signal::none())
+    }
+
+    fn debug(&self) -> Vec<(&str, String)> {
+        <dyn StatefulAction>::debug(self)
+            .into_iter()
+            .chain([
+                ("group", format!("{:?}", self.group)),
+                ("text", format!("{:?}", self.text)),
+            ])
+            .collect()
+    }
+}
@@ -1,16 +1,17 @@
 //@ stream
 
 use crate::action::{Action, Props, StatefulAction, INFINITE, VISUAL};
-use crate::comm::{QWriter, Signal};
+use crate::comm::{QWriter, Signal, SignalId};
 use crate::resource::{
     Color, IoManager, OptionalFloat, ResourceAddr, ResourceManager, ResourceValue,
 };
-use crate::server::{AsyncSignal, Config, State, SyncSignal};
+use crate::server::{register_onset, AsyncSignal, Config, State, SyncSignal};
 use crate::util::spin_sleeper;
 use eframe::egui;
 use eframe::egui::{CentralPanel, Color32, Frame, Response, TextureId, Vec2};
 use eyre::{eyre, Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 use std::path::PathBuf;
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::{mpsc, Arc, Mutex};
@@ -29,6 +30,8 @@ pub struct Video {
     background: Color,
     #[serde(default = "defaults::pad")]
     pad: bool,
+    #[serde(default)]
+    out_onset: SignalId,
 }
 
 stateful_arc!(Video {
@@ -41,6 +44,9 @@ stateful_arc!(Video {
     link: Option<(Sender<()>, Receiver<()>)>,
     background: Color32,
     pad: bool,
+    name: String,
+    out_onset: SignalId,
+    shown: bool,
 });
 
 mod defaults {
@@ -50,6 +56,11 @@ mod defaults {
 }
 
 impl Action for Video {
+    #[inline(always)]
+    fn out_signals(&self) -> BTreeSet<SignalId> {
+        BTreeSet::from([self.out_onset])
+    }
+
     #[inline(always)]
     fn resources(&self, _config: &Config) -> Vec<ResourceAddr> {
         vec![ResourceAddr::Video(self.src.clone())]
@@ -119,6 +130,9 @@ impl Action for Video {
                     link: Some((tx_start, rx_stop)),
                     background: self.background.into(),
                     pad: self.pad,
+                    name: format!("video:{}", self.src.display()),
+                    out_onset: self.out_onset,
+                    shown: false,
                 }))
             }
             _ => Err(eyre!(
@@ -186,6 +200,11 @@ impl StatefulAction for StatefulVideo {
         _async_writer: &mut QWriter<AsyncSignal>,
         _state: &State,
     ) -> Result<Response> {
+        if !self.shown {
+            self.shown = true;
+            register_onset(ui, self.name.clone(), self.out_onset);
+        }
+
         let (texture, size) = self.frames[*self.position.lock().unwrap()];
 
         let scale = if let Some(width) = self.width {
@@ -1,14 +1,22 @@
+use crate::action::trace::{is_tracing, Traced};
 use crate::action::{Action, ActionEnum, ActionEnumAsRef};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::any::TypeId;
 
 impl<'de> Deserialize<'de> for Box<dyn Action> {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        ActionEnum::deserialize(deserializer)?
+        let action = ActionEnum::deserialize(deserializer)?
             .unwrap()
-            .map_err(|e| serde::de::Error::custom(format!("{e:#?}")))
+            .map_err(|e| serde::de::Error::custom(format!("{e:#?}")))?;
+
+        if is_tracing() {
+            Ok(Box::new(Traced::new(action)))
+        } else {
+            Ok(action)
+        }
     }
 }
 
@@ -17,6 +25,12 @@ impl Serialize for Box<dyn Action> {
     where
         S: Serializer,
     {
-        ActionEnumAsRef::from(self.as_ref()).serialize(serializer)
+        let action = if self.as_ref().type_id() == TypeId::of::<Traced>() {
+            unsafe { &*(self.as_ref() as *const dyn Action as *const Traced) }.inner()
+        } else {
+            self.as_ref()
+        };
+
+        ActionEnumAsRef::from(action).serialize(serializer)
     }
 }
@@ -2,35 +2,43 @@
 // DO NOT MODIFY THIS FILE MANUALLY! CHANGES WILL BE REVERTED.
 
 include_actions!(
+    core::at@(),
     core::audio@("audio"),
     core::branch@(),
     core::clock@(),
     core::counter@(),
     core::delayed@(),
+    core::direction@(),
     core::event@(),
     core::fixation@(),
     core::function@(),
     core::horizontal@(),
     core::image@(),
     core::instruction@(),
+    core::key_hold@(),
     core::key_logger@(),
     core::logger@(),
     core::merge@(),
+    core::mouse_tracker@(),
     core::nil@(),
     core::par@(),
     core::pointer@(),
     core::process@(),
     core::question@(),
+    core::rating@(),
     core::reaction@(),
     core::rect@(),
     core::repeat@(),
     core::seq@(),
+    core::shapes@(),
     core::stack@(),
     core::stream@("stream"),
     core::switch@(),
     core::template@(),
     core::timeout@(),
     core::timer@(),
+    core::tone@("audio"),
+    core::typing@(),
     core::until@(),
     core::vertical@(),
     core::video@("stream"),
@@ -39,32 +47,39 @@ include_actions!(
 );
 
 include_stateful_actions!(
+    core::at@(),
     core::audio@("audio"),
     core::branch@(),
     core::clock@(),
     core::counter@(),
     core::delayed@(),
+    core::direction@(),
     core::event@(),
     core::function@(),
     core::image@(),
     core::instruction@(),
+    core::key_hold@(),
     core::key_logger@(),
     core::logger@(),
     core::merge@(),
+    core::mouse_tracker@(),
     core::nil@(),
     core::par@(),
     core::pointer@(),
     core::process@(),
     core::question@(),
+    core::rating@(),
     core::reaction@(),
     core::rect@(),
     core::repeat@(),
     core::seq@(),
+    core::shapes@(),
     core::stack@(),
     core::stream@("stream"),
     core::switch@(),
     core::timeout@(),
     core::timer@(),
+    core::typing@(),
     core::until@(),
     core::video@("stream"),
     core::view@(),
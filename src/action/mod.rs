@@ -5,6 +5,7 @@ pub mod de;
 pub mod extra;
 pub mod include;
 pub mod props;
+pub mod trace;
 
 pub use include::*;
 pub use props::*;
@@ -142,5 +143,114 @@ pub trait ImplStatefulAction: StatefulAction {}
 pub enum ActionSignal {
     UpdateGraph,
     KeyPress(Instant, BTreeSet<Key>),
+    KeyRelease(Instant, BTreeSet<Key>),
+    Modifiers(Instant, BTreeSet<Key>),
+    Text(Instant, String),
     StateChanged(Instant, BTreeSet<SignalId>),
 }
+
+impl ActionSignal {
+    /// For actions that opt into modifier keys: turns changes of the held modifiers into
+    /// presses of the newly held ones and releases of the others, and includes held modifiers
+    /// in every other key press to form chords.
+    pub fn with_modifiers(&self, held: &mut BTreeSet<Key>) -> Vec<ActionSignal> {
+        match self {
+            ActionSignal::Modifiers(time, modifiers) => {
+                let released: BTreeSet<_> = held.difference(modifiers).copied().collect();
+                let pressed: BTreeSet<_> = modifiers.difference(held).copied().collect();
+                *held = modifiers.clone();
+
+                let mut signals = vec![];
+                if !released.is_empty() {
+                    signals.push(ActionSignal::KeyRelease(*time, released));
+                }
+                if !pressed.is_empty() {
+                    signals.push(ActionSignal::KeyPress(*time, pressed));
+                }
+                signals
+            }
+            ActionSignal::KeyPress(time, keys) => {
+                vec![ActionSignal::KeyPress(*time, keys | held)]
+            }
+            signal => vec![signal.clone()],
+        }
+    }
+}
+
+/// Runs `handle` on a signal received by `action`. If the action opted into modifier keys
+/// (i.e. `modifiers` gives `Some` held modifiers), the signal is first converted with
+/// [`ActionSignal::with_modifiers`], and the signals returned for each conversion are merged.
+pub fn update_with_modifiers<A: ?Sized>(
+    action: &mut A,
+    modifiers: fn(&mut A) -> &mut Option<BTreeSet<Key>>,
+    signal: &ActionSignal,
+    mut handle: impl FnMut(&mut A, &ActionSignal) -> Result<Signal>,
+) -> Result<Signal> {
+    let mut held = match modifiers(action).take() {
+        Some(held) => held,
+        None => return handle(action, signal),
+    };
+
+    let mut news = vec![];
+    let mut result = Ok(());
+    for signal in signal.with_modifiers(&mut held) {
+        match handle(action, &signal) {
+            Ok(signal) => news.extend(signal),
+            Err(e) => {
+                result = Err(e);
+                break;
+            }
+        }
+    }
+
+    *modifiers(action) = Some(held);
+    result.map(|_| news.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys<const N: usize>(keys: [Key; N]) -> BTreeSet<Key> {
+        BTreeSet::from(keys)
+    }
+
+    fn presses(signals: &[ActionSignal]) -> Vec<BTreeSet<Key>> {
+        signals
+            .iter()
+            .map(|s| match s {
+                ActionSignal::KeyPress(_, keys) => keys.clone(),
+                s => panic!("Expected a key press, found {s:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn chord_is_pressed_once() {
+        let now = Instant::now();
+        let mut held = BTreeSet::new();
+
+        let ctrl = ActionSignal::Modifiers(now, keys([Key::Ctrl])).with_modifiers(&mut held);
+        assert_eq!(presses(&ctrl), vec![keys([Key::Ctrl])]);
+
+        let c = ActionSignal::KeyPress(now, keys([Key::C])).with_modifiers(&mut held);
+        assert_eq!(presses(&c), vec![keys([Key::Ctrl, Key::C])]);
+    }
+
+    #[test]
+    fn held_modifiers_are_not_pressed_again() {
+        let now = Instant::now();
+        let mut held = keys([Key::Shift]);
+
+        let alt =
+            ActionSignal::Modifiers(now, keys([Key::Shift, Key::Alt])).with_modifiers(&mut held);
+        assert_eq!(presses(&alt), vec![keys([Key::Alt])]);
+        assert_eq!(held, keys([Key::Shift, Key::Alt]));
+
+        let released = ActionSignal::Modifiers(now, keys([Key::Alt])).with_modifiers(&mut held);
+        assert!(matches!(
+            released.as_slice(),
+            [ActionSignal::KeyRelease(_, keys)] if keys == &BTreeSet::from([Key::Shift])
+        ));
+    }
+}
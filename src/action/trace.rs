@@ -0,0 +1,210 @@
+use crate::action::{Action, ActionSignal, Props, StatefulAction};
+use crate::comm::{QWriter, Signal, SignalId};
+use crate::resource::{IoManager, LoggerSignal, ResourceAddr, ResourceManager, TraceEntry};
+use crate::server::{AsyncSignal, Config, State, SyncSignal};
+use eframe::egui;
+use eframe::egui::Response;
+use eyre::Result;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeSet;
+
+thread_local! {
+    static TRACING: Cell<bool> = const { Cell::new(false) };
+    static TRACE_PATH: RefCell<Vec<(String, usize)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Enables or disables wrapping of actions deserialized in the current thread with `Traced`.
+#[inline(always)]
+pub fn set_tracing(enabled: bool) {
+    TRACING.with(|t| t.set(enabled));
+}
+
+#[inline(always)]
+pub fn is_tracing() -> bool {
+    TRACING.with(|t| t.get())
+}
+
+/// Tracing state of a thread, used to build actions on another thread (e.g. prefetched
+/// iterations) as if they were built where the state was taken.
+#[derive(Debug, Clone)]
+pub struct TraceContext {
+    tracing: bool,
+    path: Vec<(String, usize)>,
+}
+
+impl TraceContext {
+    /// Takes the tracing state of the current thread.
+    pub fn current() -> Self {
+        Self {
+            tracing: is_tracing(),
+            path: TRACE_PATH.with(|stack| stack.borrow().clone()),
+        }
+    }
+
+    /// Replaces the tracing state of the current thread with this one.
+    pub fn enter(self) {
+        set_tracing(self.tracing);
+        TRACE_PATH.with(|stack| *stack.borrow_mut() = self.path);
+    }
+}
+
+#[derive(Debug)]
+pub struct Traced(Box<dyn Action>);
+
+pub struct StatefulTraced {
+    inner: Box<dyn StatefulAction>,
+    path: String,
+    running: bool,
+}
+
+impl Traced {
+    #[inline(always)]
+    pub fn new(inner: Box<dyn Action>) -> Self {
+        Self(inner)
+    }
+
+    #[inline(always)]
+    pub fn inner(&self) -> &dyn Action {
+        self.0.as_ref()
+    }
+}
+
+impl Action for Traced {
+    #[inline(always)]
+    fn in_signals(&self) -> BTreeSet<SignalId> {
+        self.0.in_signals()
+    }
+
+    #[inline(always)]
+    fn out_signals(&self) -> BTreeSet<SignalId> {
+        self.0.out_signals()
+    }
+
+    #[inline(always)]
+    fn resources(&self, config: &Config) -> Vec<ResourceAddr> {
+        self.0.resources(config)
+    }
+
+    fn stateful(
+        &self,
+        io: &IoManager,
+        res: &ResourceManager,
+        config: &Config,
+        sync_writer: &QWriter<SyncSignal>,
+        async_writer: &QWriter<AsyncSignal>,
+    ) -> Result<Box<dyn StatefulAction>> {
+        // Path of this action in the tree is its parent's path followed by its index among
+        // the parent's (traced) children
+        let path = TRACE_PATH.with(|stack| {
+            let mut stack = stack.borrow_mut();
+            let path = match stack.last_mut() {
+                Some((parent, next)) => {
+                    let path = format!("{}/{next}", parent.trim_end_matches('/'));
+                    *next += 1;
+                    path
+                }
+                None => "/".to_owned(),
+            };
+            stack.push((path.clone(), 0));
+            path
+        });
+
+        let inner = self.0.stateful(io, res, config, sync_writer, async_writer);
+        TRACE_PATH.with(|stack| stack.borrow_mut().pop());
+
+        Ok(Box::new(StatefulTraced {
+            inner: inner?,
+            path,
+            running: false,
+        }))
+    }
+}
+
+impl StatefulTraced {
+    fn check_over(&mut self, async_writer: &mut QWriter<AsyncSignal>) -> Result<()> {
+        if self.running && self.inner.is_over()? {
+            self.running = false;
+            async_writer.push(LoggerSignal::Trace(TraceEntry::Stop(self.path.clone())));
+        }
+        Ok(())
+    }
+}
+
+impl StatefulAction for StatefulTraced {
+    #[inline(always)]
+    fn is_over(&self) -> Result<bool> {
+        self.inner.is_over()
+    }
+
+    #[inline(always)]
+    fn type_str(&self) -> String {
+        self.inner.type_str()
+    }
+
+    #[inline(always)]
+    fn props(&self) -> Props {
+        self.inner.props()
+    }
+
+    fn start(
+        &mut self,
+        sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        state: &State,
+    ) -> Result<Signal> {
+        self.running = true;
+        async_writer.push(LoggerSignal::Trace(TraceEntry::Start(
+            self.path.clone(),
+            self.inner.type_str(),
+        )));
+
+        let news = self.inner.start(sync_writer, async_writer, state)?;
+        self.check_over(async_writer)?;
+        Ok(news)
+    }
+
+    fn update(
+        &mut self,
+        signal: &ActionSignal,
+        sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        state: &State,
+    ) -> Result<Signal> {
+        let news = self
+            .inner
+            .update(signal, sync_writer, async_writer, state)?;
+        self.check_over(async_writer)?;
+        Ok(news)
+    }
+
+    fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        state: &State,
+    ) -> Result<Response> {
+        let response = self.inner.show(ui, sync_writer, async_writer, state)?;
+        self.check_over(async_writer)?;
+        Ok(response)
+    }
+
+    fn stop(
+        &mut self,
+        sync_writer: &mut QWriter<SyncSignal>,
+        async_writer: &mut QWriter<AsyncSignal>,
+        state: &State,
+    ) -> Result<Signal> {
+        let news = self.inner.stop(sync_writer, async_writer, state)?;
+        if self.running {
+            self.running = false;
+            async_writer.push(LoggerSignal::Trace(TraceEntry::Stop(self.path.clone())));
+        }
+        Ok(news)
+    }
+
+    #[inline(always)]
+    fn debug(&self) -> Vec<(&str, String)> {
+        self.inner.debug()
+    }
+}
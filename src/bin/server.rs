@@ -1,14 +1,33 @@
 use cog_task::assets::VERSION;
-use cog_task::server::Server;
-use eyre::{Context, Result};
+use cog_task::resource::{export_bids, verify_outputs, ResourceLock, TraceReplay};
+use cog_task::server::{run_benchmark, Env, Server};
+use eyre::{eyre, Context, Result};
 use sha2::{Digest, Sha256};
 use std::env::current_exe;
+use std::io::stdin;
 use std::path::PathBuf;
 
 fn main() -> Result<()> {
     let args: Vec<_> = std::env::args().collect();
+    match args.get(1).map(|s| s.as_str()) {
+        Some("bids") => return bids(&args[2..]),
+        Some("verify") => return verify(&args[2..]),
+        Some("lock") => return lock(&args[2..]),
+        Some("replay") => return replay(&args[2..]),
+        Some("benchmark") => return benchmark(&args[2..]),
+        _ => {}
+    }
+
     if args.len() != 2 {
-        println!("Invalid number of arguments. Correct usage:\n./server path_to_task_dir");
+        println!(
+            "Invalid number of arguments. Correct usage:\n\
+            ./server path_to_task_dir\n\
+            ./server bids path_to_log_dir [--sync event_name] [--out output_dir]\n\
+            ./server verify path_to_subject_output_dir\n\
+            ./server lock path_to_task_dir\n\
+            ./server replay path_to_trace_file [--step]\n\
+            ./server benchmark [--iterations n] [--out report_file]"
+        );
         std::process::exit(1);
     } else {
         println!("Starting task \"{}\" with Server-v{}...", args[1], VERSION);
@@ -28,3 +47,141 @@ fn main() -> Result<()> {
 
     Server::new(path, bin_hash)?.run()
 }
+
+fn bids(args: &[String]) -> Result<()> {
+    let mut log_dir = None;
+    let mut out_dir = None;
+    let mut sync = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--sync" => sync = args.next().cloned(),
+            "--out" => out_dir = args.next().map(PathBuf::from),
+            _ if log_dir.is_none() => log_dir = Some(PathBuf::from(arg)),
+            _ => return Err(eyre!("Unexpected argument to `bids`: {arg}")),
+        }
+    }
+
+    let log_dir = log_dir.ok_or_else(|| eyre!("Missing path to log directory for `bids`."))?;
+    let out_dir = out_dir.unwrap_or_else(|| log_dir.clone());
+    let (tsv, json) = export_bids(&log_dir, &out_dir, sync.as_deref())?;
+    println!("Exported BIDS events to: {tsv:?}\nExported BIDS sidecar to: {json:?}");
+    Ok(())
+}
+
+fn verify(args: &[String]) -> Result<()> {
+    if args.len() != 1 {
+        return Err(eyre!(
+            "`verify` expects exactly one path to a subject's output directory."
+        ));
+    }
+
+    let issues = verify_outputs(&PathBuf::from(&args[0]))?;
+    if issues.is_empty() {
+        println!("All outputs verified successfully.");
+        Ok(())
+    } else {
+        for issue in issues.iter() {
+            println!("{issue}");
+        }
+        println!("Verification failed with {} issue(s).", issues.len());
+        std::process::exit(1);
+    }
+}
+
+fn lock(args: &[String]) -> Result<()> {
+    if args.len() != 1 {
+        return Err(eyre!(
+            "`lock` expects exactly one path to a task directory."
+        ));
+    }
+
+    let task_dir = PathBuf::from(&args[0]);
+    let old = ResourceLock::load(&task_dir)?;
+    let new = ResourceLock::generate(&Env::resource_dir_of(&task_dir))?;
+
+    if old.as_ref() == Some(&new) {
+        println!("Resource lockfile is up to date.");
+    } else {
+        let diff = old.unwrap_or_default().diff(&new);
+        for line in diff.iter() {
+            println!("{line}");
+        }
+        new.write(&task_dir)?;
+        println!("Updated resource lockfile with {} change(s).", diff.len());
+    }
+    Ok(())
+}
+
+fn replay(args: &[String]) -> Result<()> {
+    let mut path = None;
+    let mut step = false;
+    for arg in args {
+        match arg.as_str() {
+            "--step" => step = true,
+            _ if path.is_none() => path = Some(PathBuf::from(arg)),
+            _ => return Err(eyre!("Unexpected argument to `replay`: {arg}")),
+        }
+    }
+
+    let path = path.ok_or_else(|| eyre!("Missing path to trace file for `replay`."))?;
+    let mut replay = TraceReplay::new(&path)?;
+    let n = replay.len();
+    let mut t0 = None;
+
+    while let Some((time, entry)) = replay.step().cloned() {
+        let t0 = *t0.get_or_insert(time);
+        println!(
+            "[{}/{n}] +{:.6}s {entry:?}",
+            replay.position(),
+            (time - t0) as f64 / 1e6
+        );
+
+        if step {
+            println!("  active: {:?}", replay.active());
+            println!("  state: {:?}", replay.state());
+
+            let mut input = String::new();
+            stdin()
+                .read_line(&mut input)
+                .wrap_err("Failed to read from stdin.")?;
+            if input.trim() == "q" {
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn benchmark(args: &[String]) -> Result<()> {
+    let mut iterations = 1000;
+    let mut out = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--iterations" => {
+                iterations = args
+                    .next()
+                    .ok_or_else(|| eyre!("Missing value for `--iterations`."))?
+                    .parse()
+                    .wrap_err("Invalid value for `--iterations`.")?
+            }
+            "--out" => out = args.next().map(PathBuf::from),
+            _ => return Err(eyre!("Unexpected argument to `benchmark`: {arg}")),
+        }
+    }
+
+    println!("Running benchmark with {iterations} iterations...");
+    let report = run_benchmark(iterations)?.to_string();
+    print!("{report}");
+
+    if let Some(out) = out {
+        std::fs::write(&out, report)
+            .wrap_err_with(|| format!("Failed to write benchmark report ({out:?})."))?;
+        println!("Saved report to: {out:?}");
+    }
+    Ok(())
+}
@@ -12,9 +12,11 @@ compile_error!("Cannot enable feature \"audio\" without a backend (\"rodio\").")
 
 #[cfg(all(
     feature = "stream",
-    not(any(feature = "gstreamer", feature = "ffmpeg"))
+    not(any(feature = "gstreamer", feature = "ffmpeg", feature = "frames"))
 ))]
-compile_error!("Cannot enable feature \"stream\" without a backend (\"gstreamer\" or \"ffmpeg\").");
+compile_error!(
+    "Cannot enable feature \"stream\" without a backend (\"gstreamer\", \"ffmpeg\" or \"frames\")."
+);
 
 macro_rules! impl_verify_features {
     ($($feature:literal),* $(,)?) => {
@@ -51,6 +53,7 @@ impl_verify_features!(
     "rodio",
     "gstreamer",
     "ffmpeg",
+    "frames",
     "savage",
     "python",
     "audio",
@@ -1,4 +1,4 @@
-use crate::resource::AudioChannel;
+use crate::resource::{AudioChannel, Synth};
 use std::path::{Path, PathBuf};
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -8,6 +8,7 @@ pub enum ResourceAddr {
     Image(PathBuf),
     Mask(PathBuf),
     Audio(PathBuf, AudioChannel),
+    Synth(Synth, AudioChannel),
     Video(PathBuf),
     Stream(PathBuf),
 }
@@ -21,6 +22,7 @@ impl ResourceAddr {
             ResourceAddr::Image(p) => p,
             ResourceAddr::Mask(p) => p,
             ResourceAddr::Audio(p, _) => p,
+            ResourceAddr::Synth(..) => Path::new(""),
             ResourceAddr::Video(p) => p,
             ResourceAddr::Stream(p) => p,
         }
@@ -34,6 +36,7 @@ impl ResourceAddr {
             ResourceAddr::Image(p) => ResourceAddr::Image(parent.join(p)),
             ResourceAddr::Mask(p) => ResourceAddr::Mask(parent.join(p)),
             ResourceAddr::Audio(p, c) => ResourceAddr::Audio(parent.join(p), *c),
+            ResourceAddr::Synth(..) => self.clone(),
             ResourceAddr::Video(p) => ResourceAddr::Video(parent.join(p)),
             ResourceAddr::Stream(p) => ResourceAddr::Stream(parent.join(p)),
         }
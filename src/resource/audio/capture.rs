@@ -0,0 +1,362 @@
+use super::rodio::{Buffer, Faded, Fades};
+use eyre::{Context, Result};
+use rodio::Source;
+use std::fmt::{Debug, Formatter};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Timeline of everything played through a capture device since it was opened, which can be
+/// mixed down into a single WAV file.
+#[derive(Clone)]
+pub struct Capture(Arc<Mutex<Timeline>>);
+
+struct Timeline {
+    origin: Instant,
+    clips: Vec<Clip>,
+}
+
+#[derive(Clone)]
+struct Clip {
+    buffer: Buffer,
+    looping: bool,
+    volume: f32,
+    fade_in: Duration,
+    fade_out: Duration,
+    start: Duration,
+    offset: Duration,
+    release: Option<Duration>,
+    stop: Option<Duration>,
+}
+
+pub struct Device(Capture);
+
+pub struct Sink {
+    capture: Capture,
+    origin: Instant,
+    queue: Vec<(Buffer, bool)>,
+    clips: Vec<usize>,
+    volume: f32,
+    fade_in: Duration,
+    fade_out: Duration,
+}
+
+impl Debug for Capture {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<Capture>")
+    }
+}
+
+impl Default for Device {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Device {
+    pub fn new() -> Self {
+        Self(Capture(Arc::new(Mutex::new(Timeline {
+            origin: Instant::now(),
+            clips: vec![],
+        }))))
+    }
+
+    #[inline(always)]
+    pub fn try_clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+
+    #[inline(always)]
+    pub fn capture(&self) -> Capture {
+        self.0.clone()
+    }
+
+    pub fn sink(&self) -> Sink {
+        Sink {
+            capture: self.0.clone(),
+            origin: self.0 .0.lock().unwrap().origin,
+            queue: vec![],
+            clips: vec![],
+            volume: 1.0,
+            fade_in: Duration::default(),
+            fade_out: Duration::default(),
+        }
+    }
+}
+
+impl Sink {
+    /// Capture cannot resume playback, so pausing a playing sink truncates its audio.
+    #[inline(always)]
+    pub fn pause(&mut self) {
+        self.stop();
+    }
+
+    /// Only applies to audio that has not started playing yet.
+    #[inline(always)]
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume;
+    }
+
+    /// Capture does not record ramps, so the volume changes at once for audio that has not
+    /// started playing yet.
+    #[inline(always)]
+    pub fn ramp_volume(&mut self, volume: f32, _duration: Duration) {
+        self.set_volume(volume);
+    }
+
+    /// Cuts the last played clip of this sink and continues it from `offset` into its buffer
+    /// (wrapped around if it is repeating).
+    pub fn seek(&mut self, offset: Duration) {
+        let now = self.origin.elapsed();
+        let mut timeline = self.capture.0.lock().unwrap();
+        let clip = match self.clips.last() {
+            Some(&i) => &mut timeline.clips[i],
+            None => return,
+        };
+        if clip.end().map_or(false, |end| end <= now) {
+            return;
+        }
+
+        let duration = clip.buffer.duration();
+        let start = now.max(clip.start);
+        let mut seeked = clip.clone();
+        clip.stop = Some(start);
+        seeked.start = start;
+        seeked.fade_in = Duration::default();
+        seeked.offset = if clip.looping && !duration.is_zero() {
+            Duration::from_secs_f64(offset.as_secs_f64() % duration.as_secs_f64())
+        } else {
+            offset.min(duration)
+        };
+
+        timeline.clips.push(seeked);
+        self.clips.push(timeline.clips.len() - 1);
+    }
+
+    #[inline(always)]
+    pub fn set_fades(&mut self, fade_in: Duration, fade_out: Duration) {
+        self.fade_in = fade_in;
+        self.fade_out = fade_out;
+    }
+
+    pub fn release(&mut self) {
+        let now = self.origin.elapsed();
+        let mut timeline = self.capture.0.lock().unwrap();
+        for &i in self.clips.iter() {
+            timeline.clips[i].release.get_or_insert(now);
+        }
+    }
+
+    #[inline(always)]
+    pub fn queue(&mut self, buffer: Buffer) {
+        self.queue.push((buffer, false));
+    }
+
+    #[inline(always)]
+    pub fn repeat(&mut self, buffer: Buffer) {
+        self.queue.push((buffer, true));
+    }
+
+    /// Audio queued while earlier audio of this sink is still playing starts right after it.
+    pub fn play(&mut self) {
+        let mut timeline = self.capture.0.lock().unwrap();
+        let now = self.origin.elapsed();
+        let mut start = self
+            .clips
+            .iter()
+            .map(|&i| &timeline.clips[i])
+            .filter(|c| c.stop.is_none() && c.release.is_none())
+            .filter_map(|c| c.end())
+            .fold(now, Duration::max);
+        for (buffer, looping) in self.queue.drain(..) {
+            let duration = buffer.duration();
+            timeline.clips.push(Clip {
+                buffer,
+                looping,
+                volume: self.volume,
+                fade_in: self.fade_in,
+                fade_out: self.fade_out,
+                start,
+                offset: Duration::default(),
+                release: None,
+                stop: None,
+            });
+            self.clips.push(timeline.clips.len() - 1);
+            start += duration;
+        }
+    }
+
+    pub fn stop(&mut self) {
+        let now = self.origin.elapsed();
+        let mut timeline = self.capture.0.lock().unwrap();
+        for &i in self.clips.iter() {
+            timeline.clips[i].stop.get_or_insert(now);
+        }
+        self.queue.clear();
+    }
+
+    pub fn empty(&self) -> bool {
+        if !self.queue.is_empty() {
+            return false;
+        }
+
+        let now = self.origin.elapsed();
+        let timeline = self.capture.0.lock().unwrap();
+        self.clips
+            .iter()
+            .all(|&i| timeline.clips[i].end().map_or(false, |end| end <= now))
+    }
+
+    /// Position of playback within the last clip of this sink that has started playing.
+    pub fn position(&self) -> Duration {
+        let now = self.origin.elapsed();
+        let timeline = self.capture.0.lock().unwrap();
+        let started = self
+            .clips
+            .iter()
+            .rev()
+            .map(|&i| &timeline.clips[i])
+            .find(|c| c.start <= now);
+        match started.or_else(|| self.clips.first().map(|&i| &timeline.clips[i])) {
+            Some(clip) => {
+                let duration = clip.buffer.duration();
+                let elapsed =
+                    clip.stop.unwrap_or(now).min(now).saturating_sub(clip.start) + clip.offset;
+                if clip.looping && !duration.is_zero() {
+                    Duration::from_secs_f64(elapsed.as_secs_f64() % duration.as_secs_f64())
+                } else {
+                    elapsed.min(duration)
+                }
+            }
+            None => Duration::default(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn detach(self) {}
+}
+
+impl Clip {
+    /// Time (since the origin) at which this clip stops being audible, if it ever does.
+    fn end(&self) -> Option<Duration> {
+        let natural = (!self.looping)
+            .then(|| self.start + self.buffer.duration().saturating_sub(self.offset));
+        [natural, self.release.map(|t| t + self.fade_out), self.stop]
+            .into_iter()
+            .flatten()
+            .min()
+    }
+
+    fn mix_into(&self, mix: &mut [f32], channels: usize, sample_rate: u32) {
+        let frames = |d: Duration| (d.as_secs_f64() * sample_rate as f64).round() as usize;
+        let fades = Fades {
+            fade_in: self.fade_in,
+            fade_out: self.fade_out,
+            release: Default::default(),
+        };
+
+        let offset = self.buffer.samples(self.offset);
+        let source: Box<dyn Source<Item = i16> + Send> =
+            Box::new(self.buffer.source(offset, self.looping));
+        let mut source = Faded::new(source, &fades).at(offset);
+
+        let in_channels = self.buffer.channels() as usize;
+        let release = self.release.map(frames);
+        let stop = self.stop.map(frames);
+        for frame in frames(self.start)..mix.len() / channels {
+            if stop.map_or(false, |s| frame >= s) {
+                break;
+            }
+            if release.map_or(false, |r| frame >= r) {
+                fades.release.store(true, Ordering::Relaxed);
+            }
+
+            for c in 0..in_channels {
+                match source.next() {
+                    Some(s) if c < channels => mix[frame * channels + c] += s as f32 * self.volume,
+                    Some(_) => {}
+                    None => return,
+                }
+            }
+        }
+    }
+}
+
+impl Capture {
+    /// Mixes all captured audio into a 16-bit PCM WAV file, starting from when the device was
+    /// opened. Audio that never ended (e.g. looping) is cut at the time of writing. Clips are
+    /// resampled to the highest sample rate among them.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let timeline = self.0.lock().unwrap();
+        let now = timeline.origin.elapsed();
+
+        let sample_rate = timeline
+            .clips
+            .iter()
+            .map(|c| c.buffer.sample_rate())
+            .max()
+            .unwrap_or(48000);
+
+        let channels = timeline
+            .clips
+            .iter()
+            .map(|c| c.buffer.channels() as usize)
+            .max()
+            .unwrap_or(1)
+            .max(1);
+        let end = timeline
+            .clips
+            .iter()
+            .map(|c| c.end().unwrap_or(now).min(now))
+            .max()
+            .unwrap_or_default();
+
+        let frames = (end.as_secs_f64() * sample_rate as f64).round() as usize;
+        let mut mix = vec![0.0; frames * channels];
+        for clip in timeline.clips.iter() {
+            if clip.buffer.sample_rate() == sample_rate {
+                clip.mix_into(&mut mix, channels, sample_rate);
+            } else {
+                let mut clip = clip.clone();
+                clip.buffer = clip
+                    .buffer
+                    .converted(None, Some(sample_rate))
+                    .wrap_err("Failed to resample captured audio.")?;
+                clip.mix_into(&mut mix, channels, sample_rate);
+            }
+        }
+
+        write_wav(path, channels as u16, sample_rate, &mix)
+            .wrap_err_with(|| format!("Failed to write captured audio to file ({path:?})."))
+    }
+}
+
+fn write_wav(path: &Path, channels: u16, sample_rate: u32, samples: &[f32]) -> Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    let data_len = (samples.len() * 2) as u32;
+    let block_align = channels * 2;
+
+    file.write_all(b"RIFF")?;
+    file.write_all(&(36 + data_len).to_le_bytes())?;
+    file.write_all(b"WAVE")?;
+    file.write_all(b"fmt ")?;
+    file.write_all(&16_u32.to_le_bytes())?;
+    file.write_all(&1_u16.to_le_bytes())?;
+    file.write_all(&channels.to_le_bytes())?;
+    file.write_all(&sample_rate.to_le_bytes())?;
+    file.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+    file.write_all(&block_align.to_le_bytes())?;
+    file.write_all(&16_u16.to_le_bytes())?;
+    file.write_all(b"data")?;
+    file.write_all(&data_len.to_le_bytes())?;
+    for &s in samples {
+        let s = s.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+        file.write_all(&s.to_le_bytes())?;
+    }
+
+    file.flush()?;
+    Ok(())
+}
@@ -0,0 +1,200 @@
+use eyre::{eyre, Result};
+use std::f64::consts::PI;
+
+/// Number of zero-crossings of the sinc kernel on each side of an interpolated sample.
+const ZERO_CROSSINGS: f64 = 32.0;
+
+/// Cutoff of the anti-aliasing filter, relative to the lower of the two Nyquist frequencies.
+const CUTOFF: f64 = 0.95;
+
+/// Filter phases are precomputed when there are at most this many of them.
+const MAX_TABLE_PHASES: u64 = 4096;
+
+/// Converts interleaved samples between channel layouts. Mono is duplicated into every output
+/// channel, and any layout is averaged down to mono.
+pub fn remix(samples: Vec<i16>, from: u16, to: u16) -> Result<Vec<i16>> {
+    match (from, to) {
+        (from, to) if from == to => Ok(samples),
+        (1, to) => Ok(samples
+            .into_iter()
+            .flat_map(|s| std::iter::repeat(s).take(to as usize))
+            .collect()),
+        (from, 1) => Ok(samples
+            .chunks(from as usize)
+            .map(|frame| {
+                let sum: i32 = frame.iter().map(|&s| s as i32).sum();
+                (sum / frame.len() as i32) as i16
+            })
+            .collect()),
+        (from, to) => Err(eyre!(
+            "Cannot convert audio with {from} channels to {to} channels (only to or from mono)."
+        )),
+    }
+}
+
+/// Resamples interleaved samples using band-limited (windowed-sinc) interpolation.
+pub fn resample(samples: &[i16], channels: u16, from: u32, to: u32) -> Vec<i16> {
+    if from == to || samples.is_empty() {
+        return samples.to_vec();
+    }
+
+    let channels = channels.max(1) as usize;
+    let frames = samples.len() / channels;
+    let g = gcd(from as u64, to as u64);
+    let (up, down) = (to as u64 / g, from as u64 / g);
+
+    // When downsampling, the cutoff is lowered to the output Nyquist frequency to avoid aliasing
+    let scale = (to as f64 / from as f64).min(1.0) * CUTOFF;
+    let half = (ZERO_CROSSINGS / scale).ceil() as i64;
+    let kernel = |phase: f64| -> Vec<f64> {
+        let mut weights: Vec<f64> = (1 - half..=half)
+            .map(|k| {
+                let x = k as f64 - phase;
+                scale * sinc(scale * x) * blackman_harris(x / (half + 1) as f64)
+            })
+            .collect();
+        let sum: f64 = weights.iter().sum();
+        weights.iter_mut().for_each(|w| *w /= sum);
+        weights
+    };
+
+    // Output frame j falls on input frame j * down / up, so there are only `up` distinct phases
+    let table: Option<Vec<Vec<f64>>> =
+        (up <= MAX_TABLE_PHASES).then(|| (0..up).map(|p| kernel(p as f64 / up as f64)).collect());
+
+    let out_frames = (frames as u64 * up + down - 1) / down;
+    let mut output = Vec::with_capacity(out_frames as usize * channels);
+    let mut computed;
+    for j in 0..out_frames {
+        let position = j * down;
+        let (i, phase) = ((position / up) as i64, position % up);
+        let weights = match table.as_ref() {
+            Some(table) => &table[phase as usize],
+            None => {
+                computed = kernel(phase as f64 / up as f64);
+                &computed
+            }
+        };
+
+        for c in 0..channels {
+            let mut acc = 0.0;
+            for (k, w) in (i + 1 - half..=i + half).zip(weights) {
+                if k >= 0 && (k as usize) < frames {
+                    acc += w * samples[k as usize * channels + c] as f64;
+                }
+            }
+            output.push(acc.round().clamp(i16::MIN as f64, i16::MAX as f64) as i16);
+        }
+    }
+
+    output
+}
+
+#[inline]
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Four-term Blackman-Harris window over [-1, 1], centered at 0.
+#[inline]
+fn blackman_harris(x: f64) -> f64 {
+    0.35875
+        + 0.48829 * (PI * x).cos()
+        + 0.14128 * (2.0 * PI * x).cos()
+        + 0.01168 * (3.0 * PI * x).cos()
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Frames ignored at each end, where the kernel reaches past the input and sees silence.
+    const EDGE: usize = 256;
+
+    fn sine(freq: f64, rate: u32, frames: usize) -> Vec<f64> {
+        (0..frames)
+            .map(|i| 10000.0 * (2.0 * PI * freq * i as f64 / rate as f64).sin())
+            .collect()
+    }
+
+    fn quantized(samples: &[f64]) -> Vec<i16> {
+        samples.iter().map(|s| s.round() as i16).collect()
+    }
+
+    #[test]
+    fn resample_length() {
+        assert_eq!(resample(&vec![0; 44100], 1, 44100, 48000).len(), 48000);
+        assert_eq!(resample(&vec![0; 48000], 1, 48000, 44100).len(), 44100);
+        assert_eq!(
+            resample(&vec![0; 2 * 44100], 2, 44100, 48000).len(),
+            2 * 48000
+        );
+        assert_eq!(
+            resample(&vec![0; 2 * 48000], 2, 48000, 44100).len(),
+            2 * 44100
+        );
+    }
+
+    #[test]
+    fn resample_same_rate() {
+        let samples = quantized(&sine(440.0, 48000, 1000));
+        assert_eq!(resample(&samples, 1, 48000, 48000), samples);
+        assert!(resample(&[], 1, 44100, 48000).is_empty());
+    }
+
+    #[test]
+    fn resample_preserves_dc() {
+        for (from, to) in [(44100, 48000), (48000, 44100)] {
+            let output = resample(&vec![10000; 2 * from as usize], 2, from, to);
+            let interior = &output[2 * EDGE..output.len() - 2 * EDGE];
+            assert!(
+                interior.iter().all(|&s| (s - 10000).abs() <= 2),
+                "DC level drifted when resampling {from} -> {to}"
+            );
+        }
+    }
+
+    #[test]
+    fn resample_preserves_sine() {
+        for (from, to) in [(44100, 48000), (48000, 44100)] {
+            let input = quantized(&sine(1000.0, from, from as usize));
+            let expected = sine(1000.0, to, to as usize);
+            let output = resample(&input, 1, from, to);
+            let error = output[EDGE..output.len() - EDGE]
+                .iter()
+                .zip(&expected[EDGE..])
+                .map(|(&s, e)| (s as f64 - e).abs())
+                .fold(0.0, f64::max);
+            assert!(
+                error < 10.0,
+                "Sine distorted by {error} when resampling {from} -> {to}"
+            );
+        }
+    }
+
+    #[test]
+    fn remix_round_trip() {
+        let mono = quantized(&sine(440.0, 48000, 1000));
+        let stereo = remix(mono.clone(), 1, 2).unwrap();
+        assert_eq!(stereo.len(), 2 * mono.len());
+        assert!(stereo.chunks(2).zip(&mono).all(|(f, &s)| f == [s, s]));
+        assert_eq!(remix(stereo, 2, 1).unwrap(), mono);
+    }
+
+    #[test]
+    fn remix_averages_to_mono() {
+        assert_eq!(remix(vec![100, 300, -50, 50], 2, 1).unwrap(), vec![200, 0]);
+        assert!(remix(vec![0; 4], 2, 4).is_err());
+    }
+}
@@ -1,12 +1,24 @@
 use crate::server::Config;
+use cfg_if::cfg_if;
+#[cfg(feature = "rodio")]
+use eyre::Context;
 use eyre::{eyre, Result};
 use serde::{Deserialize, Serialize};
 use std::fmt::{Debug, Formatter};
 use std::path::Path;
 use std::time::Duration;
 
+#[cfg(feature = "rodio")]
+mod capture;
+#[cfg(feature = "rodio")]
+mod convert;
 #[cfg(feature = "rodio")]
 mod rodio;
+mod synth;
+
+#[cfg(feature = "rodio")]
+pub use capture::Capture;
+pub use synth::*;
 
 #[derive(Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
@@ -16,6 +28,30 @@ pub enum AudioChannel {
     Right,
 }
 
+/// Gains from each source channel (columns) to each output channel (rows).
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct AudioRouting(Vec<Vec<f32>>);
+
+/// Audio output device, either by its index or its name (see the system info dialog).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum AudioOutput {
+    Inherit,
+    Index(usize),
+    Name(String),
+}
+
+/// Channel layout that audio is converted to when loaded.
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioFormat {
+    Inherit,
+    Native,
+    Mono,
+    Stereo,
+}
+
 #[derive(Debug, Copy, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum AudioBackend {
@@ -23,6 +59,8 @@ pub enum AudioBackend {
     Inherit,
     #[cfg(feature = "rodio")]
     Rodio,
+    #[cfg(feature = "rodio")]
+    Capture,
 }
 
 #[derive(Clone)]
@@ -36,12 +74,16 @@ pub enum AudioSink {
     None,
     #[cfg(feature = "rodio")]
     Rodio(rodio::Sink),
+    #[cfg(feature = "rodio")]
+    Capture(capture::Sink),
 }
 
 pub enum AudioDevice {
     None,
     #[cfg(feature = "rodio")]
     Rodio(rodio::Device),
+    #[cfg(feature = "rodio")]
+    Capture(capture::Device),
 }
 
 impl AudioDevice {
@@ -49,7 +91,31 @@ impl AudioDevice {
         match self {
             AudioDevice::None => Ok(AudioDevice::None),
             #[cfg(feature = "rodio")]
-            AudioDevice::Rodio(_) => rodio::Device::new().map(AudioDevice::Rodio),
+            AudioDevice::Rodio(device) => {
+                rodio::Device::new(device.output().clone(), device.channels())
+                    .map(AudioDevice::Rodio)
+            }
+            #[cfg(feature = "rodio")]
+            AudioDevice::Capture(device) => Ok(AudioDevice::Capture(device.try_clone())),
+        }
+    }
+
+    /// Number of output channels of the opened device. Captured audio has no fixed layout.
+    pub fn output_channels(&self) -> Option<u16> {
+        match self {
+            #[cfg(feature = "rodio")]
+            AudioDevice::Rodio(device) => Some(device.output_channels()),
+            _ => None,
+        }
+    }
+
+    /// Timeline of the audio played on this device, if it is being captured.
+    #[cfg(feature = "rodio")]
+    pub fn capture(&self) -> Option<Capture> {
+        match self {
+            #[cfg(feature = "rodio")]
+            AudioDevice::Capture(device) => Some(device.capture()),
+            _ => None,
         }
     }
 }
@@ -77,6 +143,99 @@ impl AudioBackend {
     }
 }
 
+impl Default for AudioFormat {
+    #[inline(always)]
+    fn default() -> Self {
+        AudioFormat::Inherit
+    }
+}
+
+impl AudioFormat {
+    pub fn or(&self, other: &Self) -> Self {
+        if let Self::Inherit = self {
+            *other
+        } else {
+            *self
+        }
+    }
+
+    /// Number of channels of this layout, if it is not the native one of the audio.
+    pub fn channels(&self) -> Option<u16> {
+        match self {
+            AudioFormat::Inherit | AudioFormat::Native => None,
+            AudioFormat::Mono => Some(1),
+            AudioFormat::Stereo => Some(2),
+        }
+    }
+}
+
+impl Default for AudioOutput {
+    #[inline(always)]
+    fn default() -> Self {
+        AudioOutput::Inherit
+    }
+}
+
+impl AudioOutput {
+    pub fn or(&self, other: &Self) -> Self {
+        if let Self::Inherit = self {
+            other.clone()
+        } else {
+            self.clone()
+        }
+    }
+}
+
+impl AudioRouting {
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Number of output channels.
+    #[inline(always)]
+    pub fn outputs(&self) -> u16 {
+        self.0.len() as u16
+    }
+
+    /// Number of source channels referred to by the matrix.
+    #[inline]
+    pub fn inputs(&self) -> u16 {
+        self.0.iter().map(|row| row.len()).max().unwrap_or(0) as u16
+    }
+
+    /// Gain from source channel `input` to output channel `output` (missing entries are 0).
+    #[inline]
+    pub fn gain(&self, output: usize, input: usize) -> f32 {
+        self.0
+            .get(output)
+            .and_then(|row| row.get(input))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// Pads the matrix with silent output channels up to `outputs`.
+    pub fn padded(mut self, outputs: u16) -> Self {
+        while self.0.len() < outputs as usize {
+            self.0.push(vec![]);
+        }
+        self
+    }
+
+    pub fn verify(&self) -> Result<()> {
+        if self.0.len() > u16::MAX as usize {
+            return Err(eyre!(
+                "Audio routing cannot have more than {} output channels.",
+                u16::MAX
+            ));
+        }
+        if self.0.iter().flatten().any(|g| !g.is_finite()) {
+            return Err(eyre!("Audio routing gains should be finite numbers."));
+        }
+        Ok(())
+    }
+}
+
 #[derive(Copy, Clone, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum Volume {
@@ -133,6 +292,57 @@ impl From<f32> for Volume {
     }
 }
 
+/// Duration of a fade in seconds.
+#[derive(Copy, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Fade {
+    Inherit,
+    Value(f32),
+}
+
+impl Default for Fade {
+    #[inline(always)]
+    fn default() -> Self {
+        Fade::Inherit
+    }
+}
+
+impl Debug for Fade {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Fade::Value(dur) = self {
+            write!(f, "{dur}s")
+        } else {
+            write!(f, "inherit")
+        }
+    }
+}
+
+impl Fade {
+    pub fn or(&self, other: &Self) -> Self {
+        if let Self::Inherit = self {
+            *other
+        } else {
+            *self
+        }
+    }
+
+    pub fn value(&self) -> Duration {
+        match self {
+            &Self::Value(x) if x.is_finite() && x > 0.0 => Duration::from_secs_f32(x),
+            _ => Duration::default(),
+        }
+    }
+
+    pub fn verify(&self) -> Result<()> {
+        match self {
+            &Self::Value(x) if !x.is_finite() || x < 0.0 => Err(eyre!(
+                "Fade duration should be a non-negative number of seconds ({x})."
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum TimePrecision {
@@ -158,13 +368,54 @@ impl TimePrecision {
     }
 }
 
+/// Names of the available audio output devices, in the order used for indexing them.
+pub fn audio_output_devices() -> Result<Vec<String>> {
+    cfg_if! {
+        if #[cfg(feature = "rodio")] {
+            rodio::output_devices()
+        } else {
+            Ok(vec![])
+        }
+    }
+}
+
+/// Measures the start latency of the audio backend on a sink without an output device.
+pub fn null_start_latency() -> Result<Duration> {
+    cfg_if! {
+        if #[cfg(feature = "rodio")] {
+            rodio::null_start_latency()
+        } else {
+            Err(eyre!("Measuring audio start latency requires the rodio backend."))
+        }
+    }
+}
+
 #[allow(unused_variables)]
 pub fn audio_from_file(path: &Path, config: &Config) -> Result<AudioBuffer> {
     match config.audio_backend() {
         AudioBackend::None => Err(eyre!("Cannot load audio file with backend=None.")),
         AudioBackend::Inherit => Err(eyre!("Cannot load audio file with backend=Inherit.")),
         #[cfg(feature = "rodio")]
-        AudioBackend::Rodio => rodio::Buffer::new(path, config).map(AudioBuffer::Rodio),
+        AudioBackend::Rodio | AudioBackend::Capture => rodio::Buffer::new(path, config)
+            .map(AudioBuffer::Rodio)?
+            .normalized(config)
+            .wrap_err_with(|| format!("Failed to convert audio file: {path:?}")),
+    }
+}
+
+/// Synthesizes a (mono) audio buffer from its specification.
+#[allow(unused_variables)]
+pub fn audio_from_synth(synth: &Synth, config: &Config) -> Result<AudioBuffer> {
+    synth.verify()?;
+    match config.audio_backend() {
+        AudioBackend::None => Err(eyre!("Cannot synthesize audio with backend=None.")),
+        AudioBackend::Inherit => Err(eyre!("Cannot synthesize audio with backend=Inherit.")),
+        #[cfg(feature = "rodio")]
+        AudioBackend::Rodio | AudioBackend::Capture => AudioBuffer::Rodio(
+            rodio::Buffer::from_samples(1, synth.sample_rate, synth.samples()),
+        )
+        .normalized(config)
+        .wrap_err("Failed to convert synthesized audio."),
     }
 }
 
@@ -193,6 +444,27 @@ impl AudioBuffer {
         }
     }
 
+    /// Converts the buffer to the sample rate and channel layout requested by the config, if
+    /// they differ from its own.
+    pub fn normalized(self, config: &Config) -> Result<AudioBuffer> {
+        let channels = config
+            .audio_format()
+            .channels()
+            .filter(|&c| c != self.channels());
+        let sample_rate = config
+            .audio_sample_rate()
+            .filter(|&r| r != self.sample_rate());
+        if channels.is_none() && sample_rate.is_none() {
+            return Ok(self);
+        }
+
+        match self {
+            #[cfg(feature = "rodio")]
+            AudioBuffer::Rodio(x) => x.converted(channels, sample_rate).map(AudioBuffer::Rodio),
+            _ => Err(eyre!("Cannot convert audio buffer with backend=None.")),
+        }
+    }
+
     pub fn interlaced(self, other: AudioBuffer) -> Result<AudioBuffer> {
         match (self, other) {
             #[cfg(feature = "rodio")]
@@ -203,6 +475,40 @@ impl AudioBuffer {
         }
     }
 
+    /// Interlaces a trigger into extra channels of this buffer so both are played by the same
+    /// sink, sample-aligned. With `Left`/`Right`, the (mono) audio is sent to that side and the
+    /// (mono) trigger to the other one; with `Stereo`, trigger channels follow those of the audio.
+    pub fn with_trigger(self, trigger: AudioBuffer, channel: AudioChannel) -> Result<AudioBuffer> {
+        if channel != AudioChannel::Stereo && (self.channels() > 1 || trigger.channels() > 1) {
+            return Err(eyre!(
+                "Routing audio with trigger to the {channel:?} channel is only supported for mono \
+                audio and trigger buffers."
+            ));
+        }
+
+        match channel {
+            AudioChannel::Stereo | AudioChannel::Left => self.interlaced(trigger),
+            AudioChannel::Right => trigger.interlaced(self),
+        }
+    }
+
+    /// Mixes source channels into the output channels of `routing`, each with its own gain.
+    pub fn routed(self, routing: &AudioRouting) -> Result<AudioBuffer> {
+        if routing.inputs() > self.channels() {
+            return Err(eyre!(
+                "Audio routing refers to {} source channels, but audio only has {}.",
+                routing.inputs(),
+                self.channels()
+            ));
+        }
+
+        match self {
+            #[cfg(feature = "rodio")]
+            AudioBuffer::Rodio(x) => x.routed(routing).map(AudioBuffer::Rodio),
+            _ => Err(eyre!("Cannot route audio buffer with backend=None.")),
+        }
+    }
+
     pub fn with_direction(self, channel: AudioChannel) -> Result<AudioBuffer> {
         if channel == AudioChannel::Stereo {
             return Ok(self);
@@ -233,6 +539,11 @@ impl AudioSink {
                 sink.pause();
                 Ok(())
             }
+            #[cfg(feature = "rodio")]
+            AudioSink::Capture(sink) => {
+                sink.pause();
+                Ok(())
+            }
         }
     }
 
@@ -245,6 +556,93 @@ impl AudioSink {
                 sink.set_volume(volume);
                 Ok(())
             }
+            #[cfg(feature = "rodio")]
+            AudioSink::Capture(sink) => {
+                sink.set_volume(volume);
+                Ok(())
+            }
+        }
+    }
+
+    /// Linearly changes the volume from its current value to `volume` over `duration`.
+    #[allow(unused_variables)]
+    pub fn ramp_volume(&mut self, volume: f32, duration: Duration) -> Result<()> {
+        match self {
+            AudioSink::None => Err(eyre!("Cannot ramp volume of audio sink with backend=None.")),
+            #[cfg(feature = "rodio")]
+            AudioSink::Rodio(sink) => {
+                sink.ramp_volume(volume, duration);
+                Ok(())
+            }
+            #[cfg(feature = "rodio")]
+            AudioSink::Capture(sink) => {
+                sink.ramp_volume(volume, duration);
+                Ok(())
+            }
+        }
+    }
+
+    /// Jumps to `offset` into the queued audio, wrapping around if it is repeating.
+    #[allow(unused_variables)]
+    pub fn seek(&mut self, offset: Duration) -> Result<()> {
+        match self {
+            AudioSink::None => Err(eyre!("Cannot seek audio sink with backend=None.")),
+            #[cfg(feature = "rodio")]
+            AudioSink::Rodio(sink) => {
+                sink.seek(offset);
+                Ok(())
+            }
+            #[cfg(feature = "rodio")]
+            AudioSink::Capture(sink) => {
+                sink.seek(offset);
+                Ok(())
+            }
+        }
+    }
+
+    /// Position of playback within the queued audio.
+    pub fn position(&self) -> Result<Duration> {
+        match self {
+            AudioSink::None => Ok(Duration::default()),
+            #[cfg(feature = "rodio")]
+            AudioSink::Rodio(sink) => Ok(sink.position()),
+            #[cfg(feature = "rodio")]
+            AudioSink::Capture(sink) => Ok(sink.position()),
+        }
+    }
+
+    /// Sets the fade-in and fade-out durations for audio queued after this call.
+    #[allow(unused_variables)]
+    pub fn set_fades(&mut self, fade_in: Duration, fade_out: Duration) -> Result<()> {
+        match self {
+            AudioSink::None => Err(eyre!("Cannot set fades on audio sink with backend=None.")),
+            #[cfg(feature = "rodio")]
+            AudioSink::Rodio(sink) => {
+                sink.set_fades(fade_in, fade_out);
+                Ok(())
+            }
+            #[cfg(feature = "rodio")]
+            AudioSink::Capture(sink) => {
+                sink.set_fades(fade_in, fade_out);
+                Ok(())
+            }
+        }
+    }
+
+    /// Fades out the queued audio, after which the sink becomes empty.
+    pub fn release(&mut self) -> Result<()> {
+        match self {
+            AudioSink::None => Err(eyre!("Cannot release audio sink with backend=None.")),
+            #[cfg(feature = "rodio")]
+            AudioSink::Rodio(sink) => {
+                sink.release();
+                Ok(())
+            }
+            #[cfg(feature = "rodio")]
+            AudioSink::Capture(sink) => {
+                sink.release();
+                Ok(())
+            }
         }
     }
 
@@ -256,6 +654,11 @@ impl AudioSink {
                 sink.queue(buffer);
                 Ok(())
             }
+            #[cfg(feature = "rodio")]
+            (AudioSink::Capture(sink), AudioBuffer::Rodio(buffer)) => {
+                sink.queue(buffer);
+                Ok(())
+            }
             #[allow(unreachable_patterns)]
             (_, _) => Err(eyre!("Cannot queue audio on incompatible sink.")),
         }
@@ -269,6 +672,11 @@ impl AudioSink {
                 sink.repeat(buffer);
                 Ok(())
             }
+            #[cfg(feature = "rodio")]
+            (AudioSink::Capture(sink), AudioBuffer::Rodio(buffer)) => {
+                sink.repeat(buffer);
+                Ok(())
+            }
             #[allow(unreachable_patterns)]
             (_, _) => Err(eyre!("Cannot repeat audio on incompatible sink.")),
         }
@@ -282,6 +690,11 @@ impl AudioSink {
                 sink.play();
                 Ok(())
             }
+            #[cfg(feature = "rodio")]
+            AudioSink::Capture(sink) => {
+                sink.play();
+                Ok(())
+            }
         }
     }
 
@@ -293,6 +706,11 @@ impl AudioSink {
                 sink.stop();
                 Ok(())
             }
+            #[cfg(feature = "rodio")]
+            AudioSink::Capture(sink) => {
+                sink.stop();
+                Ok(())
+            }
         }
     }
 
@@ -301,6 +719,26 @@ impl AudioSink {
             AudioSink::None => Ok(true),
             #[cfg(feature = "rodio")]
             AudioSink::Rodio(sink) => Ok(sink.empty()),
+            #[cfg(feature = "rodio")]
+            AudioSink::Capture(sink) => Ok(sink.empty()),
+        }
+    }
+
+    /// Wraps interleaved samples (e.g. decoded from a stream) in a buffer that can be queued on
+    /// this sink.
+    #[allow(unused_variables)]
+    pub fn buffer(
+        &self,
+        channels: u16,
+        sample_rate: u32,
+        samples: Vec<i16>,
+    ) -> Result<AudioBuffer> {
+        match self {
+            AudioSink::None => Err(eyre!("Cannot create audio buffer with backend=None.")),
+            #[cfg(feature = "rodio")]
+            AudioSink::Rodio(_) | AudioSink::Capture(_) => Ok(AudioBuffer::Rodio(
+                rodio::Buffer::from_samples(channels, sample_rate, samples),
+            )),
         }
     }
 
@@ -312,6 +750,11 @@ impl AudioSink {
                 sink.detach();
                 Ok(())
             }
+            #[cfg(feature = "rodio")]
+            AudioSink::Capture(sink) => {
+                sink.detach();
+                Ok(())
+            }
         }
     }
 }
@@ -319,10 +762,14 @@ impl AudioSink {
 impl AudioDevice {
     pub fn new(config: &Config) -> Result<Self> {
         match config.audio_backend() {
-            AudioBackend::None => Err(eyre!("Cannot obtain audio device with backend=None.")),
+            AudioBackend::None => Ok(Self::None),
             AudioBackend::Inherit => Err(eyre!("Cannot obtain audio device with backend=None.")),
             #[cfg(feature = "rodio")]
-            AudioBackend::Rodio => rodio::Device::new().map(Self::Rodio),
+            AudioBackend::Rodio => {
+                rodio::Device::new(config.audio_device(), config.audio_channels()).map(Self::Rodio)
+            }
+            #[cfg(feature = "rodio")]
+            AudioBackend::Capture => Ok(Self::Capture(capture::Device::new())),
         }
     }
 
@@ -331,6 +778,8 @@ impl AudioDevice {
             AudioDevice::None => Err(eyre!("Cannot create audio sink with backend=None.")),
             #[cfg(feature = "rodio")]
             AudioDevice::Rodio(device) => device.sink().map(AudioSink::Rodio),
+            #[cfg(feature = "rodio")]
+            AudioDevice::Capture(device) => Ok(AudioSink::Capture(device.sink())),
         }
     }
 }
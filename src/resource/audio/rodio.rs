@@ -1,30 +1,113 @@
-use crate::resource::AudioChannel;
+use super::convert::{remix, resample};
+use crate::resource::{AudioChannel, AudioOutput, AudioRouting};
 use crate::server::Config;
+use crate::util::spin_sleeper;
 use eyre::{eyre, Context, Result};
 use rodio::buffer::SamplesBuffer;
-use rodio::source::Buffered;
-use rodio::{Decoder, OutputStream, OutputStreamHandle, Source};
+use rodio::cpal::traits::{DeviceTrait, HostTrait};
+use rodio::{cpal, Decoder, OutputStream, OutputStreamHandle, Source};
 use std::fs::File;
 use std::io::BufReader;
 use std::path::Path;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+/// Interleaved samples, along with their number of channels and sample rate.
 #[derive(Clone)]
-pub struct Buffer(Buffered<SamplesBuffer<i16>>);
-pub struct Sink(rodio::Sink);
-pub struct Device(OutputStream, OutputStreamHandle);
+pub struct Buffer(Arc<[i16]>, u16, u32);
+pub struct Sink(rodio::Sink, Fades, Arc<Control>, Option<(Buffer, bool)>);
+pub struct Device(
+    OutputStream,
+    OutputStreamHandle,
+    AudioOutput,
+    Option<u16>,
+    u16,
+);
 
 impl Device {
-    pub fn new() -> Result<Self> {
-        let (audio_stream, audio_stream_handle) =
-            OutputStream::try_default().wrap_err("Failed to obtain audio output stream.")?;
-        Ok(Self(audio_stream, audio_stream_handle))
+    pub fn new(output: AudioOutput, channels: Option<u16>) -> Result<Self> {
+        let (audio_stream, audio_stream_handle) = match (&output, channels) {
+            (AudioOutput::Inherit, None) => {
+                OutputStream::try_default().wrap_err("Failed to obtain audio output stream.")?
+            }
+            (_, None) => OutputStream::try_from_device(&find_device(&output)?)
+                .wrap_err_with(|| format!("Failed to obtain audio output stream ({output:?})."))?,
+            (_, Some(channels)) => {
+                let device = find_device(&output)?;
+                let default_rate = device
+                    .default_output_config()
+                    .wrap_err("Failed to obtain default audio output config.")?
+                    .sample_rate();
+
+                // Prefer a config that supports the default sample rate of the device
+                let configs: Vec<_> = device
+                    .supported_output_configs()
+                    .wrap_err("Failed to obtain supported audio output configs.")?
+                    .filter(|c| c.channels() == channels)
+                    .collect();
+                let preferred = configs
+                    .iter()
+                    .find(|c| {
+                        c.min_sample_rate() <= default_rate && default_rate <= c.max_sample_rate()
+                    })
+                    .cloned();
+                let config = match preferred {
+                    Some(c) => c.with_sample_rate(default_rate),
+                    None => configs
+                        .into_iter()
+                        .next()
+                        .ok_or_else(|| {
+                            eyre!("Audio output device ({output:?}) does not support {channels} channels.")
+                        })?
+                        .with_max_sample_rate(),
+                };
+
+                OutputStream::try_from_device_config(&device, config).wrap_err_with(|| {
+                    format!("Failed to obtain audio output stream with {channels} channels.")
+                })?
+            }
+        };
+        // Without a requested channel count, the device is opened with its default config
+        let output_channels = match channels {
+            Some(channels) => channels,
+            None => find_device(&output)?
+                .default_output_config()
+                .wrap_err("Failed to obtain default audio output config.")?
+                .channels(),
+        };
+
+        Ok(Self(
+            audio_stream,
+            audio_stream_handle,
+            output,
+            channels,
+            output_channels,
+        ))
+    }
+
+    /// Output device requested by the config.
+    #[inline(always)]
+    pub fn output(&self) -> &AudioOutput {
+        &self.2
+    }
+
+    /// Number of output channels requested from the device, if any.
+    #[inline(always)]
+    pub fn channels(&self) -> Option<u16> {
+        self.3
+    }
+
+    /// Number of output channels the device was opened with.
+    #[inline(always)]
+    pub fn output_channels(&self) -> u16 {
+        self.4
     }
 
     pub fn sink(&self) -> Result<Sink> {
         let sink = rodio::Sink::try_new(&self.1)?;
         sink.pause();
-        Ok(Sink(sink))
+        Ok(Sink(sink, Fades::default(), Arc::new(Control::new()), None))
     }
 }
 
@@ -36,17 +119,75 @@ impl Sink {
 
     #[inline(always)]
     pub fn set_volume(&self, volume: f32) {
-        self.0.set_volume(volume);
+        self.2.request(|r| {
+            r.volume = volume;
+            r.ramp = Duration::default();
+        });
     }
 
+    /// Linearly changes the volume from its current value to `volume` over `duration`.
     #[inline(always)]
-    pub fn queue(&self, buffer: Buffer) {
-        self.0.append(buffer.0);
+    pub fn ramp_volume(&self, volume: f32, duration: Duration) {
+        self.2.request(|r| {
+            r.volume = volume;
+            r.ramp = duration;
+        });
     }
 
+    /// Jumps to `offset` into the last queued buffer (wrapped around if it is repeating).
+    pub fn seek(&self, offset: Duration) {
+        if let Some((buffer, looping)) = self.3.as_ref() {
+            let fades = Fades {
+                fade_in: Duration::default(),
+                fade_out: self.1.fade_out,
+                release: self.1.release.clone(),
+            };
+
+            let mut position = buffer.samples(offset);
+            if *looping {
+                position %= buffer.period().max(1);
+            }
+            let source: BoxedSource = Box::new(buffer.source(position, *looping));
+            let source = Faded::new(source, &fades).at(position);
+
+            self.2.request(|r| r.seek = Some((source, position)));
+        }
+    }
+
+    /// Position of playback within the currently playing buffer.
+    #[inline(always)]
+    pub fn position(&self) -> Duration {
+        Duration::from_micros(self.2.position.load(Ordering::Relaxed))
+    }
+
+    /// Sets the fades applied to sources queued after this call.
     #[inline(always)]
-    pub fn repeat(&self, buffer: Buffer) {
-        self.0.append(buffer.0.repeat_infinite());
+    pub fn set_fades(&mut self, fade_in: Duration, fade_out: Duration) {
+        self.1.fade_in = fade_in;
+        self.1.fade_out = fade_out;
+    }
+
+    /// Starts fading out the queued sources, after which the sink becomes empty.
+    #[inline(always)]
+    pub fn release(&self) {
+        self.1.release.store(true, Ordering::Relaxed);
+    }
+
+    pub fn queue(&mut self, buffer: Buffer) {
+        let source: BoxedSource = Box::new(buffer.source(0, false));
+        self.0
+            .append(Controlled::new(Faded::new(source, &self.1), &self.2, None));
+        self.3 = Some((buffer, false));
+    }
+
+    pub fn repeat(&mut self, buffer: Buffer) {
+        let source: BoxedSource = Box::new(buffer.source(0, true));
+        self.0.append(Controlled::new(
+            Faded::new(source, &self.1),
+            &self.2,
+            Some(buffer.period()),
+        ));
+        self.3 = Some((buffer, true));
     }
 
     #[inline(always)]
@@ -70,6 +211,375 @@ impl Sink {
     }
 }
 
+type BoxedSource = Box<dyn Source<Item = i16> + Send>;
+
+#[derive(Default)]
+pub(super) struct Fades {
+    pub(super) fade_in: Duration,
+    pub(super) fade_out: Duration,
+    pub(super) release: Arc<AtomicBool>,
+}
+
+/// Source with a linear fade-in at its start, and a linear fade-out either at its end (if
+/// finite) or as soon as it is released, after which it ends.
+pub(super) struct Faded<S> {
+    inner: S,
+    channels: u64,
+    fade_in: u64,
+    fade_out: u64,
+    end: Option<u64>,
+    release: Arc<AtomicBool>,
+    released: Option<(u64, f32)>,
+    position: u64,
+}
+
+impl<S: Source<Item = i16>> Faded<S> {
+    pub(super) fn new(inner: S, fades: &Fades) -> Self {
+        // Fades are counted in frames, i.e. samples of all channels at a given time
+        let rate = inner.sample_rate() as f64;
+        let frames = |d: Duration| (d.as_secs_f64() * rate).round() as u64;
+
+        Self {
+            channels: inner.channels().max(1) as u64,
+            fade_in: frames(fades.fade_in),
+            fade_out: frames(fades.fade_out),
+            end: inner.total_duration().map(frames),
+            release: fades.release.clone(),
+            released: None,
+            position: 0,
+            inner,
+        }
+    }
+
+    /// Treats the inner source as starting `samples` into the audio, e.g. after seeking.
+    pub(super) fn at(mut self, samples: u64) -> Self {
+        self.position = samples;
+        self
+    }
+
+    fn gain(&mut self) -> Option<f32> {
+        let frame = self.position / self.channels;
+        let mut gain = 1.0_f32;
+        if frame < self.fade_in {
+            gain = frame as f32 / self.fade_in as f32;
+        }
+        if let (Some(end), true) = (self.end, self.fade_out > 0) {
+            gain = gain.min(end.saturating_sub(frame) as f32 / self.fade_out as f32);
+        }
+
+        // Only released on frame boundaries so the source ends with a complete frame
+        if self.released.is_none()
+            && self.position % self.channels == 0
+            && self.release.load(Ordering::Relaxed)
+        {
+            self.released = Some((frame, gain));
+        }
+        if let Some((at, start)) = self.released {
+            let elapsed = frame - at;
+            if elapsed >= self.fade_out && self.position % self.channels == 0 {
+                return None;
+            }
+            let remaining = 1.0 - elapsed as f32 / self.fade_out.max(1) as f32;
+            gain = gain.min(start * remaining.max(0.0));
+        }
+
+        Some(gain)
+    }
+}
+
+impl<S: Source<Item = i16>> Iterator for Faded<S> {
+    type Item = i16;
+
+    #[inline]
+    fn next(&mut self) -> Option<i16> {
+        let gain = self.gain()?;
+        let sample = self.inner.next()?;
+        self.position += 1;
+        if gain < 1.0 {
+            Some((sample as f32 * gain) as i16)
+        } else {
+            Some(sample)
+        }
+    }
+}
+
+impl<S: Source<Item = i16>> Source for Faded<S> {
+    #[inline(always)]
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    #[inline(always)]
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    #[inline(always)]
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    #[inline(always)]
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+/// Source that plays a buffer from a given sample onwards, wrapping around at its end if
+/// looping. Its total duration is that of the whole buffer, regardless of where it starts.
+pub(super) struct Samples {
+    buffer: Buffer,
+    position: usize,
+    looping: bool,
+}
+
+impl Iterator for Samples {
+    type Item = i16;
+
+    #[inline]
+    fn next(&mut self) -> Option<i16> {
+        if self.position >= self.buffer.0.len() {
+            if !self.looping || self.buffer.0.is_empty() {
+                return None;
+            }
+            self.position = 0;
+        }
+
+        let sample = self.buffer.0[self.position];
+        self.position += 1;
+        Some(sample)
+    }
+}
+
+impl Source for Samples {
+    #[inline(always)]
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    #[inline(always)]
+    fn channels(&self) -> u16 {
+        self.buffer.channels()
+    }
+
+    #[inline(always)]
+    fn sample_rate(&self) -> u32 {
+        self.buffer.sample_rate()
+    }
+
+    #[inline(always)]
+    fn total_duration(&self) -> Option<Duration> {
+        (!self.looping).then(|| self.buffer.duration())
+    }
+}
+
+/// Requests made to a sink that are picked up by its playing source on the audio thread.
+pub(super) struct Control {
+    requests: Mutex<Requests>,
+    pending: AtomicBool,
+    position: AtomicU64,
+}
+
+struct Requests {
+    volume: f32,
+    ramp: Duration,
+    seek: Option<(Faded<BoxedSource>, u64)>,
+}
+
+impl Control {
+    fn new() -> Self {
+        Self {
+            requests: Mutex::new(Requests {
+                volume: 1.0,
+                ramp: Duration::default(),
+                seek: None,
+            }),
+            pending: AtomicBool::new(false),
+            position: AtomicU64::new(0),
+        }
+    }
+
+    fn request(&self, f: impl FnOnce(&mut Requests)) {
+        f(&mut self.requests.lock().unwrap());
+        self.pending.store(true, Ordering::Release);
+    }
+}
+
+/// Source whose volume (with linear ramps) and position can be changed while it is playing.
+/// Playback position is published in microseconds, wrapped around every `period` samples.
+struct Controlled {
+    inner: Faded<BoxedSource>,
+    control: Arc<Control>,
+    channels: u64,
+    sample_rate: u32,
+    period: Option<u64>,
+    position: u64,
+    gain: Option<f32>,
+    target: f32,
+    step: f32,
+}
+
+impl Controlled {
+    fn new(inner: Faded<BoxedSource>, control: &Arc<Control>, period: Option<u64>) -> Self {
+        Self {
+            channels: inner.channels().max(1) as u64,
+            sample_rate: inner.sample_rate(),
+            inner,
+            control: control.clone(),
+            period,
+            position: 0,
+            gain: None,
+            target: 1.0,
+            step: 0.0,
+        }
+    }
+
+    /// Applies pending requests; only called on frame boundaries.
+    fn apply(&mut self) {
+        let mut requests = self.control.requests.lock().unwrap();
+        if let Some((inner, position)) = requests.seek.take() {
+            self.inner = inner;
+            self.position = position;
+        }
+
+        let frames = (requests.ramp.as_secs_f64() * self.sample_rate as f64).round() as f32;
+        let gain = *self.gain.get_or_insert(requests.volume);
+        self.target = requests.volume;
+        self.step = if frames >= 1.0 {
+            (self.target - gain) / frames
+        } else {
+            self.gain = Some(self.target);
+            0.0
+        };
+    }
+}
+
+impl Iterator for Controlled {
+    type Item = i16;
+
+    #[inline]
+    fn next(&mut self) -> Option<i16> {
+        if self.position % self.channels == 0 {
+            if self.gain.is_none() || self.control.pending.swap(false, Ordering::Acquire) {
+                self.apply();
+            }
+
+            let mut gain = self.gain.unwrap_or(1.0);
+            if gain != self.target {
+                gain += self.step;
+                if (self.step > 0.0 && gain > self.target)
+                    || (self.step < 0.0 && gain < self.target)
+                {
+                    gain = self.target;
+                }
+                self.gain = Some(gain);
+            }
+
+            let position = match self.period {
+                Some(period) => self.position % period.max(1),
+                None => self.position,
+            };
+            let micros = (position / self.channels) * 1_000_000 / self.sample_rate.max(1) as u64;
+            self.control.position.store(micros, Ordering::Relaxed);
+        }
+
+        let sample = self.inner.next()?;
+        self.position += 1;
+        match self.gain {
+            Some(gain) if gain != 1.0 => Some((sample as f32 * gain) as i16),
+            _ => Some(sample),
+        }
+    }
+}
+
+impl Source for Controlled {
+    #[inline(always)]
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    #[inline(always)]
+    fn channels(&self) -> u16 {
+        self.channels as u16
+    }
+
+    #[inline(always)]
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    #[inline(always)]
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Names of the available output devices, in the order used for indexing them.
+pub fn output_devices() -> Result<Vec<String>> {
+    cpal::default_host()
+        .output_devices()
+        .wrap_err("Failed to enumerate audio output devices.")?
+        .map(|d| {
+            d.name()
+                .wrap_err("Failed to obtain name of audio output device.")
+        })
+        .collect()
+}
+
+fn find_device(output: &AudioOutput) -> Result<cpal::Device> {
+    let host = cpal::default_host();
+    let device = match output {
+        AudioOutput::Inherit => host.default_output_device(),
+        AudioOutput::Index(i) => host
+            .output_devices()
+            .wrap_err("Failed to enumerate audio output devices.")?
+            .nth(*i),
+        AudioOutput::Name(name) => host
+            .output_devices()
+            .wrap_err("Failed to enumerate audio output devices.")?
+            .find(|d| d.name().map(|n| &n == name).unwrap_or(false)),
+    };
+
+    device.ok_or_else(|| {
+        let available = output_devices()
+            .unwrap_or_default()
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| format!("\n  {i}: {name}"))
+            .collect::<String>();
+        eyre!("Failed to find audio output device ({output:?}). Available devices:{available}")
+    })
+}
+
+/// Measures the time between starting a sink that is not connected to any output device and
+/// its first non-silent sample. Output is pulled in 1ms chunks like a real-time device would.
+pub fn null_start_latency() -> Result<Duration> {
+    let sample_rate = 48000;
+    let channels = 2;
+    let (sink, mut output) = rodio::Sink::new_idle();
+    sink.pause();
+    sink.append(SamplesBuffer::new(
+        channels,
+        sample_rate,
+        vec![i16::MAX / 2; sample_rate as usize * channels as usize],
+    ));
+
+    let sleeper = spin_sleeper();
+    let chunk = (sample_rate / 1000) as usize * channels as usize;
+    let start = Instant::now();
+    sink.play();
+    while start.elapsed() < Duration::from_secs(1) {
+        for _ in 0..chunk {
+            if output.next().unwrap_or(0.0) != 0.0 {
+                return Ok(start.elapsed());
+            }
+        }
+        sleeper.sleep(Duration::from_millis(1));
+    }
+
+    Err(eyre!("Null audio sink did not start within 1s."))
+}
+
 impl Buffer {
     pub fn new(path: &Path, _config: &Config) -> Result<Self> {
         let decoder = Decoder::new(BufReader::new(
@@ -78,43 +588,84 @@ impl Buffer {
         .wrap_err_with(|| format!("Failed to decode audio file: {path:?}"))?;
 
         let sample_rate = decoder.sample_rate();
-        let channels = decoder.channels() as i16;
+        let channels = decoder.channels();
         let samples: Vec<_> = decoder.collect();
 
-        Ok(Self(
-            SamplesBuffer::new(channels as u16, sample_rate, samples).buffered(),
-        ))
+        Ok(Self::from_samples(channels, sample_rate, samples))
     }
 
     #[inline(always)]
+    pub fn from_samples(channels: u16, sample_rate: u32, samples: Vec<i16>) -> Self {
+        Self(samples.into(), channels, sample_rate)
+    }
+
+    #[inline]
     pub fn duration(&self) -> Duration {
-        self.0.total_duration().unwrap_or_default()
+        let rate = self.2 as u64 * self.1.max(1) as u64;
+        Duration::from_nanos(self.0.len() as u64 * 1_000_000_000 / rate.max(1))
     }
 
     #[inline(always)]
     pub fn sample_rate(&self) -> u32 {
-        self.0.sample_rate()
+        self.2
     }
 
     #[inline(always)]
     pub fn channels(&self) -> u16 {
-        self.0.channels()
+        self.1
     }
 
-    pub fn interlaced(mut self, mut other: Self) -> Result<Self> {
-        let sample_rate = self.0.sample_rate();
-        let in_channels = self.0.channels() as i16;
-        let other_channels = other.0.channels() as i16;
+    /// Source that plays this buffer from the `position`-th sample (of all channels) onwards.
+    #[inline(always)]
+    pub(super) fn source(&self, position: u64, looping: bool) -> Samples {
+        Samples {
+            buffer: self.clone(),
+            position: position as usize,
+            looping,
+        }
+    }
+
+    /// Number of samples (of all channels) that make up the first `offset` of this buffer.
+    #[inline]
+    pub(super) fn samples(&self, offset: Duration) -> u64 {
+        let frames = (offset.as_secs_f64() * self.sample_rate() as f64).round() as u64;
+        frames * self.channels() as u64
+    }
+
+    /// Number of samples (of all channels) in this buffer.
+    #[inline(always)]
+    pub(super) fn period(&self) -> u64 {
+        self.samples(self.duration())
+    }
+
+    pub fn converted(self, channels: Option<u16>, sample_rate: Option<u32>) -> Result<Self> {
+        let in_channels = self.channels();
+        let in_rate = self.sample_rate();
+        let out_channels = channels.unwrap_or(in_channels);
+        let out_rate = sample_rate.unwrap_or(in_rate);
+
+        let samples = remix(self.0.to_vec(), in_channels, out_channels)?;
+        let samples = resample(&samples, out_channels, in_rate, out_rate);
+        Ok(Self::from_samples(out_channels, out_rate, samples))
+    }
+
+    pub fn interlaced(self, other: Self) -> Result<Self> {
+        let sample_rate = self.sample_rate();
+        let in_channels = self.channels() as i16;
+        let other_channels = other.channels() as i16;
         let out_channels = in_channels + other_channels;
 
-        if other.0.sample_rate() != sample_rate {
+        if other.sample_rate() != sample_rate {
             return Err(eyre!(
                 "Cannot interlace audio buffers with different sampling rates: {}, {}",
                 sample_rate,
-                other.0.sample_rate(),
+                other.sample_rate(),
             ));
         }
 
+        let mut ours = self.0.iter().copied();
+        let mut theirs = other.0.iter().copied();
+
         let mut c = -1;
         let mut samples = vec![];
         let mut status = 0;
@@ -125,23 +676,46 @@ impl Buffer {
             }
 
             samples.push(if c < in_channels {
-                self.0.next().unwrap_or_else(|| {
+                ours.next().unwrap_or_else(|| {
                     status |= 1;
                     0
                 })
             } else {
-                other.0.next().unwrap_or_else(|| {
+                theirs.next().unwrap_or_else(|| {
                     status |= 2;
                     0
                 })
             });
         }
 
-        Ok(Self(
-            SamplesBuffer::new(out_channels as u16, sample_rate, samples).buffered(),
+        Ok(Self::from_samples(
+            out_channels as u16,
+            sample_rate,
+            samples,
         ))
     }
 
+    pub fn routed(self, routing: &AudioRouting) -> Result<Self> {
+        let sample_rate = self.sample_rate();
+        let in_channels = self.channels() as usize;
+        let out_channels = routing.outputs() as usize;
+
+        let samples = &self.0;
+        let mut routed = Vec::with_capacity(samples.len() / in_channels.max(1) * out_channels);
+        for frame in samples.chunks(in_channels.max(1)) {
+            for o in 0..out_channels {
+                let s: f32 = frame
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &s)| routing.gain(o, i) * s as f32)
+                    .sum();
+                routed.push(s.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16);
+            }
+        }
+
+        Ok(Self::from_samples(out_channels as u16, sample_rate, routed))
+    }
+
     pub fn with_direction(self, channel: AudioChannel) -> Result<Self> {
         let sample_rate = self.sample_rate();
         let out_channels = match (self.channels(), channel) {
@@ -155,7 +729,7 @@ impl Buffer {
         };
 
         let mut samples = vec![];
-        for s in self.0 {
+        for &s in self.0.iter() {
             if channel == AudioChannel::Right {
                 samples.push(0);
             }
@@ -165,8 +739,6 @@ impl Buffer {
             }
         }
 
-        Ok(Self(
-            SamplesBuffer::new(out_channels, sample_rate, samples).buffered(),
-        ))
+        Ok(Self::from_samples(out_channels, sample_rate, samples))
     }
 }
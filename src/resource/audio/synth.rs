@@ -0,0 +1,255 @@
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use std::hash::{Hash, Hasher};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Waveform {
+    Sine(f32),
+    Chirp {
+        from: f32,
+        to: f32,
+        #[serde(default)]
+        exponential: bool,
+    },
+    Am {
+        carrier: f32,
+        modulator: f32,
+        #[serde(default = "defaults::depth")]
+        depth: f32,
+    },
+    Fm {
+        carrier: f32,
+        modulator: f32,
+        deviation: f32,
+    },
+    WhiteNoise,
+    PinkNoise,
+    Clicks {
+        rate: f32,
+        #[serde(default = "defaults::click_width")]
+        width: f32,
+    },
+}
+
+/// Specification of a synthesized mono sound.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Synth {
+    pub wave: Waveform,
+    pub duration: f32,
+    pub level: f32,
+    pub ramp: f32,
+    pub sample_rate: u32,
+    pub seed: u64,
+}
+
+mod defaults {
+    #[inline(always)]
+    pub fn depth() -> f32 {
+        1.0
+    }
+
+    #[inline(always)]
+    pub fn click_width() -> f32 {
+        0.0001
+    }
+}
+
+// Specs are validated to be free of NaNs before being used as resource addresses
+impl Eq for Synth {}
+
+impl Hash for Synth {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        format!("{self:?}").hash(state);
+    }
+}
+
+impl Waveform {
+    fn frequencies(&self) -> Vec<f32> {
+        match self {
+            Waveform::Sine(f) => vec![*f],
+            Waveform::Chirp { from, to, .. } => vec![*from, *to],
+            Waveform::Am {
+                carrier, modulator, ..
+            } => vec![*carrier, *modulator],
+            Waveform::Fm {
+                carrier,
+                modulator,
+                deviation,
+            } => vec![*carrier, *modulator, carrier + deviation],
+            Waveform::Clicks { rate, .. } => vec![*rate],
+            Waveform::WhiteNoise | Waveform::PinkNoise => vec![],
+        }
+    }
+}
+
+impl Synth {
+    pub fn verify(&self) -> Result<()> {
+        let nyquist = self.sample_rate as f32 / 2.0;
+        if self.sample_rate == 0 {
+            return Err(eyre!(
+                "Synthesized audio should have a positive sample rate."
+            ));
+        }
+        if !self.duration.is_finite() || self.duration <= 0.0 {
+            return Err(eyre!(
+                "Synthesized audio should have a positive duration ({}).",
+                self.duration
+            ));
+        }
+        if !self.level.is_finite() || self.level > 0.0 {
+            return Err(eyre!(
+                "Level of synthesized audio should be a non-positive dBFS value ({}).",
+                self.level
+            ));
+        }
+        if !self.ramp.is_finite() || self.ramp < 0.0 || 2.0 * self.ramp > self.duration {
+            return Err(eyre!(
+                "Ramp of synthesized audio should be between zero and half its duration ({}).",
+                self.ramp
+            ));
+        }
+        for f in self.wave.frequencies() {
+            if !f.is_finite() || f <= 0.0 || f >= nyquist {
+                return Err(eyre!(
+                    "Frequencies of synthesized audio should be between 0 and {nyquist}Hz ({f})."
+                ));
+            }
+        }
+        match self.wave {
+            Waveform::Am { depth, .. } if !(0.0..=1.0).contains(&depth) => Err(eyre!(
+                "Modulation depth of AM tone should be between 0.0 and 1.0 ({depth})."
+            )),
+            Waveform::Clicks { rate, width } if !(width > 0.0 && width * rate < 1.0) => Err(eyre!(
+                "Width of clicks should be positive and shorter than their period ({width})."
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Generates the (mono) samples of this sound, with raised-cosine onset and offset ramps.
+    pub fn samples(&self) -> Vec<i16> {
+        let rate = self.sample_rate as f64;
+        let len = (self.duration as f64 * rate).round() as usize;
+        let amplitude = 10_f64.powf(self.level as f64 / 20.0) * i16::MAX as f64;
+        let mut rng = XorShift::new(self.seed);
+
+        let mut samples: Vec<f64> = match self.wave {
+            Waveform::Sine(f) => (0..len)
+                .map(|i| (2.0 * PI * f as f64 * i as f64 / rate).sin())
+                .collect(),
+            Waveform::Chirp {
+                from,
+                to,
+                exponential,
+            } => {
+                // Phase is the integral of the instantaneous frequency
+                let (f0, f1, dur) = (from as f64, to as f64, self.duration as f64);
+                (0..len)
+                    .map(|i| {
+                        let t = i as f64 / rate;
+                        let phase = if exponential && f0 != f1 {
+                            let k = (f1 / f0).ln() / dur;
+                            f0 * ((k * t).exp() - 1.0) / k
+                        } else {
+                            f0 * t + (f1 - f0) * t * t / (2.0 * dur)
+                        };
+                        (2.0 * PI * phase).sin()
+                    })
+                    .collect()
+            }
+            Waveform::Am {
+                carrier,
+                modulator,
+                depth,
+            } => (0..len)
+                .map(|i| {
+                    let t = i as f64 / rate;
+                    let envelope = 1.0 + depth as f64 * (2.0 * PI * modulator as f64 * t).sin();
+                    envelope * (2.0 * PI * carrier as f64 * t).sin() / (1.0 + depth as f64)
+                })
+                .collect(),
+            Waveform::Fm {
+                carrier,
+                modulator,
+                deviation,
+            } => (0..len)
+                .map(|i| {
+                    let t = i as f64 / rate;
+                    let index = deviation as f64 / modulator as f64;
+                    (2.0 * PI * carrier as f64 * t
+                        + index * (2.0 * PI * modulator as f64 * t).sin())
+                    .sin()
+                })
+                .collect(),
+            Waveform::WhiteNoise => (0..len).map(|_| rng.uniform()).collect(),
+            Waveform::PinkNoise => {
+                // Paul Kellet's refined filter for pink noise
+                let mut b = [0.0; 7];
+                let noise: Vec<f64> = (0..len)
+                    .map(|_| {
+                        let white = rng.uniform();
+                        b[0] = 0.99886 * b[0] + white * 0.0555179;
+                        b[1] = 0.99332 * b[1] + white * 0.0750759;
+                        b[2] = 0.96900 * b[2] + white * 0.1538520;
+                        b[3] = 0.86650 * b[3] + white * 0.3104856;
+                        b[4] = 0.55000 * b[4] + white * 0.5329522;
+                        b[5] = -0.7616 * b[5] - white * 0.0168980;
+                        let pink = b.iter().sum::<f64>() + white * 0.5362;
+                        b[6] = white * 0.115926;
+                        pink
+                    })
+                    .collect();
+                let peak = noise.iter().fold(0.0_f64, |m, s| m.max(s.abs()));
+                noise.into_iter().map(|s| s / peak.max(1e-9)).collect()
+            }
+            Waveform::Clicks { rate: r, width } => {
+                let period = rate / r as f64;
+                let width = (width as f64 * rate).round().max(1.0);
+                (0..len)
+                    .map(|i| {
+                        if (i as f64 % period) < width {
+                            1.0
+                        } else {
+                            0.0
+                        }
+                    })
+                    .collect()
+            }
+        };
+
+        let ramp = (self.ramp as f64 * rate).round() as usize;
+        let gains: Vec<f64> = (0..ramp.min(len))
+            .map(|i| 0.5 - 0.5 * (PI * i as f64 / ramp as f64).cos())
+            .collect();
+        for (s, g) in samples.iter_mut().zip(&gains) {
+            *s *= g;
+        }
+        for (s, g) in samples.iter_mut().rev().zip(&gains) {
+            *s *= g;
+        }
+
+        samples
+            .into_iter()
+            .map(|s| (s * amplitude).round() as i16)
+            .collect()
+    }
+}
+
+/// Small deterministic generator, so that noise is reproducible for a given seed.
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        Self((seed ^ 0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    /// Uniformly distributed number in [-1, 1).
+    fn uniform(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1_u64 << 52) as f64 - 1.0
+    }
+}
@@ -0,0 +1,281 @@
+use crate::resource::{parse_log_time, read_log};
+use crate::server::Info;
+use chrono::{DateTime, FixedOffset};
+use eyre::{eyre, Context, Result};
+use serde_cbor::Value;
+use serde_json::json;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MISSING: &str = "n/a";
+
+/// Entries of each log file of a run, by file stem.
+type Logs = HashMap<String, Vec<(String, String, Value)>>;
+
+#[derive(Debug)]
+struct BidsEvent {
+    onset: DateTime<FixedOffset>,
+    duration: Option<f64>,
+    trial_type: String,
+    value: String,
+    response_time: Option<f64>,
+}
+
+impl BidsEvent {
+    fn new(onset: DateTime<FixedOffset>, trial_type: &str, value: &str) -> Self {
+        Self {
+            onset,
+            duration: None,
+            trial_type: trial_type.to_owned(),
+            value: value.to_owned(),
+            response_time: None,
+        }
+    }
+
+    fn to_row(&self, sync: DateTime<FixedOffset>) -> String {
+        let onset = seconds_between(sync, self.onset);
+        let duration = self
+            .duration
+            .map(|d| format!("{d:.6}"))
+            .unwrap_or_else(|| MISSING.to_owned());
+        let response_time = self
+            .response_time
+            .map(|rt| format!("{rt:.6}"))
+            .unwrap_or_else(|| MISSING.to_owned());
+
+        format!(
+            "{onset:.6}\t{duration}\t{}\t{}\t{response_time}",
+            sanitized_field(&self.trial_type),
+            sanitized_field(&self.value),
+        )
+    }
+}
+
+/// Exports the logs of a single block run (`log_dir`) as a BIDS `_events.tsv` file together
+/// with its JSON sidecar. Onsets are measured relative to the start of the `event` named
+/// `sync`, or to the start of the block if no sync event is given.
+pub fn export_bids(
+    log_dir: &Path,
+    out_dir: &Path,
+    sync: Option<&str>,
+) -> Result<(PathBuf, PathBuf)> {
+    let logs = read_log_dir(log_dir)?;
+
+    let main = logs
+        .get("main")
+        .ok_or_else(|| eyre!("Failed to find main log in directory ({log_dir:?})."))?;
+
+    let info: Info = main
+        .iter()
+        .find(|(_, name, _)| name == "info")
+        .map(|(_, _, value)| serde_cbor::value::from_value(value.clone()))
+        .ok_or_else(|| eyre!("Failed to find block info in main log."))?
+        .wrap_err("Failed to parse block info in main log.")?;
+
+    let sync_time = if let Some(sync) = sync {
+        logs.get("event")
+            .and_then(|entries| {
+                entries.iter().find(|(_, name, value)| {
+                    name == sync && matches!(value, Value::Text(v) if v == "start")
+                })
+            })
+            .map(|(time, _, _)| parse_log_time(time))
+            .ok_or_else(|| eyre!("Failed to find start of sync event `{sync}` in event log."))??
+    } else {
+        main.iter()
+            .find(|(_, name, _)| name == "start")
+            .map(|(time, _, _)| parse_log_time(time))
+            .ok_or_else(|| eyre!("Failed to find block start time in main log."))??
+    };
+
+    let mut events = vec![];
+    for (group, entries) in logs.iter() {
+        match group.as_str() {
+            "main" => {}
+            "event" => events.extend(paired_events(entries, None)?),
+            "stimulus" => {
+                for (time, name, _) in entries {
+                    events.push(BidsEvent::new(parse_log_time(time)?, "stimulus", name));
+                }
+            }
+            _ => events.extend(group_events(group, entries)?),
+        }
+    }
+    events.sort_by_key(|e| e.onset);
+
+    let mut tsv = "onset\tduration\ttrial_type\tvalue\tresponse_time\n".to_owned();
+    for event in events.iter() {
+        writeln!(tsv, "{}", event.to_row(sync_time)).unwrap();
+    }
+
+    let sidecar = json!({
+        "TaskName": info.task(),
+        "onset": {
+            "Description": "Onset of the event relative to the sync event.",
+            "Units": "s",
+        },
+        "duration": {
+            "Description": "Duration of the event, if it has one.",
+            "Units": "s",
+        },
+        "trial_type": {
+            "Description": "Event name, logger group, or one of: stimulus, key_press, key_release, response.",
+        },
+        "value": {
+            "Description": "Stimulus name, pressed key(s), or response correctness.",
+        },
+        "response_time": {
+            "Description": "Reaction time relative to the closest target.",
+            "Units": "s",
+        },
+        "SyncEvent": sync.unwrap_or("start"),
+        "StimulusPresentation": {
+            "SoftwareName": "cog-task",
+        },
+        "CogTask": info,
+    });
+
+    let prefix = format!(
+        "sub-{}_task-{}",
+        bids_label(info.subject()),
+        bids_label(info.task())
+    );
+    let tsv_path = out_dir.join(format!("{prefix}_events.tsv"));
+    let json_path = out_dir.join(format!("{prefix}_events.json"));
+
+    fs::create_dir_all(out_dir)
+        .wrap_err_with(|| format!("Failed to create BIDS output directory ({out_dir:?})."))?;
+    fs::write(&tsv_path, tsv)
+        .wrap_err_with(|| format!("Failed to write BIDS events file ({tsv_path:?})."))?;
+    fs::write(
+        &json_path,
+        serde_json::to_string_pretty(&sidecar).wrap_err("Failed to serialize BIDS sidecar.")?,
+    )
+    .wrap_err_with(|| format!("Failed to write BIDS sidecar file ({json_path:?})."))?;
+
+    Ok((tsv_path, json_path))
+}
+
+fn read_log_dir(log_dir: &Path) -> Result<Logs> {
+    let mut logs = HashMap::new();
+    let dir = fs::read_dir(log_dir)
+        .wrap_err_with(|| format!("Failed to read log directory ({log_dir:?})."))?;
+
+    for entry in dir {
+        let path = entry?.path();
+        if path.extension().and_then(|s| s.to_str()) != Some("log") {
+            continue;
+        }
+
+        // Files that are not entry lists (e.g. crash reports) are not event sources
+        if let Ok(entries) = read_log(&path) {
+            let group = path.file_stem().unwrap().to_string_lossy().to_string();
+            logs.insert(group, entries);
+        }
+    }
+
+    Ok(logs)
+}
+
+fn paired_events(
+    entries: &[(String, String, Value)],
+    trial_type: Option<&str>,
+) -> Result<Vec<BidsEvent>> {
+    let mut events: Vec<BidsEvent> = vec![];
+    let mut open: HashMap<&str, Vec<usize>> = HashMap::new();
+
+    for (time, name, value) in entries {
+        let time = parse_log_time(time)?;
+        match value {
+            Value::Text(v) if v == "start" => {
+                open.entry(name.as_str()).or_default().push(events.len());
+                events.push(BidsEvent::new(time, trial_type.unwrap_or(name), ""));
+            }
+            Value::Text(v) if v == "stop" => {
+                if let Some(i) = open.get_mut(name.as_str()).and_then(|v| v.pop()) {
+                    events[i].duration = Some(seconds_between(events[i].onset, time));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(events)
+}
+
+fn group_events(group: &str, entries: &[(String, String, Value)]) -> Result<Vec<BidsEvent>> {
+    let mut events = paired_events(
+        &entries
+            .iter()
+            .filter(|(_, name, _)| name == "event")
+            .cloned()
+            .collect::<Vec<_>>(),
+        Some(group),
+    )?;
+
+    for (time, name, value) in entries {
+        match (name.as_str(), value) {
+            ("key", Value::Array(keys)) | ("release", Value::Array(keys)) => {
+                let keys: Vec<_> = keys
+                    .iter()
+                    .filter_map(|k| match k {
+                        Value::Text(k) => Some(k.as_str()),
+                        _ => None,
+                    })
+                    .collect();
+                if !keys.is_empty() {
+                    events.push(BidsEvent::new(
+                        parse_log_time(time)?,
+                        if name == "key" {
+                            "key_press"
+                        } else {
+                            "key_release"
+                        },
+                        &keys.join("+"),
+                    ));
+                }
+            }
+            ("correct", Value::Array(v)) => {
+                let mut event = BidsEvent::new(parse_log_time(time)?, "response", "correct");
+                if let Some(Value::Float(rt)) = v.get(1) {
+                    event.response_time = Some(*rt);
+                }
+                events.push(event);
+            }
+            ("incorrect", _) => {
+                events.push(BidsEvent::new(
+                    parse_log_time(time)?,
+                    "response",
+                    "incorrect",
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(events)
+}
+
+#[inline(always)]
+fn seconds_between(from: DateTime<FixedOffset>, to: DateTime<FixedOffset>) -> f64 {
+    (to - from).num_microseconds().unwrap_or_default() as f64 / 1e6
+}
+
+#[inline(always)]
+fn sanitized_field(field: &str) -> String {
+    if field.is_empty() {
+        MISSING.to_owned()
+    } else {
+        field.replace(['\t', '\n'], " ")
+    }
+}
+
+#[inline(always)]
+fn bids_label(label: &str) -> String {
+    label
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect()
+}
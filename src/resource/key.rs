@@ -1,29 +1,36 @@
 use eframe::egui;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 
 macro_rules! key {
-    ($($name:ident),* $(,)?) => {
+    ($($name:ident),* $(,)?; $($modifier:ident),* $(,)?) => {
         #[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
         #[serde(rename_all = "snake_case")]
         pub enum Key {
             $($name,)*
+            $($modifier,)*
         }
 
-        impl From<&Key> for egui::Key {
+        impl TryFrom<&Key> for egui::Key {
+            type Error = ();
+
             #[inline]
-            fn from(k: &Key) -> Self {
+            fn try_from(k: &Key) -> Result<Self, ()> {
                 match k {
                     $(
-                        Key::$name => egui::Key::$name,
+                        Key::$name => Ok(egui::Key::$name),
                     )*
+                    _ => Err(()),
                 }
             }
         }
 
-        impl From<Key> for egui::Key {
+        impl TryFrom<Key> for egui::Key {
+            type Error = ();
+
             #[inline(always)]
-            fn from(k: Key) -> Self {
-                Self::from(&k)
+            fn try_from(k: Key) -> Result<Self, ()> {
+                Self::try_from(&k)
             }
         }
 
@@ -44,6 +51,13 @@ macro_rules! key {
                 Self::from(&k)
             }
         }
+
+        impl Key {
+            #[inline]
+            pub fn is_modifier(&self) -> bool {
+                matches!(self, $(Key::$modifier)|*)
+            }
+        }
     }
 }
 
@@ -52,5 +66,27 @@ key!(
     Delete, Home, End, PageUp, PageDown, Num0, Num1, Num2, Num3, Num4, Num5, Num6, Num7, Num8,
     Num9, Minus, PlusEquals, A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W,
     X, Y, Z, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, F13, F14, F15, F16, F17, F18, F19,
-    F20,
+    F20;
+    Shift, Ctrl, Alt, Command,
 );
+
+impl Key {
+    /// Modifier keys that are held down according to `modifiers`. egui does not report
+    /// modifiers as key events, so they are tracked through the modifier state instead.
+    pub fn modifiers(modifiers: &egui::Modifiers) -> BTreeSet<Key> {
+        let mut keys = BTreeSet::new();
+        if modifiers.shift {
+            keys.insert(Key::Shift);
+        }
+        if modifiers.ctrl {
+            keys.insert(Key::Ctrl);
+        }
+        if modifiers.alt {
+            keys.insert(Key::Alt);
+        }
+        if modifiers.mac_cmd {
+            keys.insert(Key::Command);
+        }
+        keys
+    }
+}
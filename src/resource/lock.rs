@@ -0,0 +1,164 @@
+use crate::util::file_hash;
+use eyre::{eyre, Context, Result};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const LOCK_FILE: &str = "resources.lock";
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ResourceLock(BTreeMap<PathBuf, String>);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockPolicy {
+    Inherit,
+    Ignore,
+    Warn,
+    Enforce,
+}
+
+impl Default for LockPolicy {
+    #[inline(always)]
+    fn default() -> Self {
+        LockPolicy::Inherit
+    }
+}
+
+impl LockPolicy {
+    pub fn or(&self, other: &Self) -> Self {
+        if let Self::Inherit = self {
+            *other
+        } else {
+            *self
+        }
+    }
+}
+
+impl ResourceLock {
+    /// Loads the lockfile located in the task directory, if there is one.
+    pub fn load(task_dir: &Path) -> Result<Option<Self>> {
+        let path = task_dir.join(LOCK_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&path)
+            .wrap_err_with(|| format!("Failed to read resource lockfile ({path:?})."))?;
+        ron::from_str(&content)
+            .map(Some)
+            .wrap_err_with(|| format!("Failed to parse resource lockfile ({path:?})."))
+    }
+
+    /// Hashes every file under the resource directory.
+    pub fn generate(resource_dir: &Path) -> Result<Self> {
+        let mut lock = Self::default();
+        lock.insert_dir(resource_dir, resource_dir)?;
+        Ok(lock)
+    }
+
+    pub fn write(&self, task_dir: &Path) -> Result<()> {
+        let path = task_dir.join(LOCK_FILE);
+        let content = ron::ser::to_string_pretty(&self, PrettyConfig::default())
+            .wrap_err("Failed to serialize resource lockfile.")?;
+        fs::write(&path, content)
+            .wrap_err_with(|| format!("Failed to write resource lockfile ({path:?})."))
+    }
+
+    /// Returns a human-readable list of differences between this lock and `other`.
+    pub fn diff(&self, other: &Self) -> Vec<String> {
+        let mut diff = vec![];
+        for (path, hash) in other.0.iter() {
+            match self.0.get(path) {
+                None => diff.push(format!("+ {path:?}")),
+                Some(h) if h != hash => diff.push(format!("~ {path:?}")),
+                _ => {}
+            }
+        }
+        for path in self.0.keys() {
+            if !other.0.contains_key(path) {
+                diff.push(format!("- {path:?}"));
+            }
+        }
+        diff
+    }
+
+    /// Checks a resource (`rel_path` relative to the resource directory) against its hash
+    /// on file. Directory resources (e.g. image sequences) are checked file by file. Depending
+    /// on `policy`, a mismatch is either ignored, printed or returned as an error.
+    pub fn verify(&self, rel_path: &Path, abs_path: &Path, policy: LockPolicy) -> Result<()> {
+        if matches!(policy, LockPolicy::Inherit | LockPolicy::Ignore) || !abs_path.exists() {
+            return Ok(());
+        }
+
+        let mut issues = vec![];
+        if abs_path.is_dir() {
+            let mut files = Self::default();
+            files.insert_dir(abs_path, abs_path)?;
+            let files: BTreeMap<_, _> = files
+                .0
+                .into_iter()
+                .map(|(path, hash)| (rel_path.join(path), hash))
+                .collect();
+
+            for (path, hash) in files.iter() {
+                issues.extend(self.issue(path, hash));
+            }
+            for path in self.0.keys() {
+                if path.starts_with(rel_path) && !files.contains_key(path) {
+                    issues.push(format!(
+                        "Resource listed in {LOCK_FILE} is missing: {path:?}"
+                    ));
+                }
+            }
+        } else {
+            issues.extend(self.issue(rel_path, &file_hash(abs_path)?));
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else if let LockPolicy::Enforce = policy {
+            Err(eyre!(issues.join("\n")))
+        } else {
+            for issue in issues {
+                println!("WARNING: {issue}");
+            }
+            Ok(())
+        }
+    }
+
+    fn issue(&self, rel_path: &Path, hash: &str) -> Option<String> {
+        match self.0.get(rel_path) {
+            None => Some(format!(
+                "Resource is not listed in {LOCK_FILE}: {rel_path:?}"
+            )),
+            Some(h) if h != hash => Some(format!(
+                "Resource does not match its hash in {LOCK_FILE}: {rel_path:?}"
+            )),
+            _ => None,
+        }
+    }
+
+    fn insert_dir(&mut self, root: &Path, dir: &Path) -> Result<()> {
+        for entry in fs::read_dir(dir)
+            .wrap_err_with(|| format!("Failed to read resource directory ({dir:?})."))?
+        {
+            let path = entry?.path();
+            let name = path.file_name().unwrap().to_string_lossy();
+            if name.starts_with('.') || name == LOCK_FILE {
+                continue;
+            }
+
+            if path.is_dir() {
+                self.insert_dir(root, &path)?;
+            } else {
+                let rel_path = path.strip_prefix(root).unwrap().to_owned();
+                self.0.insert(rel_path, file_hash(&path)?);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,7 +1,10 @@
 use crate::action::Action;
 use crate::comm::QWriter;
+#[cfg(feature = "rodio")]
+use crate::resource::Capture;
+use crate::resource::{Manifest, TraceEntry, TRACE_FILE};
 use crate::server::{AsyncSignal, Config, Info};
-use chrono::{DateTime, Local};
+use chrono::{DateTime, FixedOffset, Local};
 use eyre::{eyre, Context, Error, Result};
 use itertools::Itertools;
 use ron::ser::PrettyConfig;
@@ -9,24 +12,32 @@ use serde::{Deserialize, Serialize, Serializer};
 use serde_cbor::{from_slice, Value};
 use std::collections::HashMap;
 use std::fmt::Debug;
-use std::fs::{create_dir_all, File};
-use std::io::Write;
-use std::path::PathBuf;
+use std::fs::{create_dir_all, File, OpenOptions};
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use std::{fs, thread};
 
 pub const TAG_INFO: u64 = 0x01;
 pub const TAG_CONFIG: u64 = 0x02;
 pub const TAG_ACTION: u64 = 0x03;
+#[cfg(feature = "rodio")]
+pub const CAPTURE_FILE: &str = "audio_capture.wav";
 
-pub type LogGroup = (Vec<(String, String, Value)>, bool);
+/// Entries of a log group, along with the number of them that have been written to disk.
+pub type LogGroup = (Vec<(String, String, Value)>, usize);
 
 #[derive(Debug, Default)]
 pub struct Logger {
+    info: Info,
     out_dir: PathBuf,
     content: HashMap<String, LogGroup>,
     needs_flush: bool,
     log_format: LogFormat,
+    log_flush: LogFlush,
+    trace: Option<BufWriter<File>>,
+    #[cfg(feature = "rodio")]
+    capture: Option<Capture>,
 }
 
 #[derive(Debug, Clone)]
@@ -34,6 +45,9 @@ pub enum LoggerSignal {
     Append(String, (String, Value)),
     Extend(String, Vec<(String, Value)>),
     Write(String, Value),
+    Trace(TraceEntry),
+    #[cfg(feature = "rodio")]
+    Capture(Capture),
     Flush,
 }
 
@@ -46,6 +60,22 @@ pub enum LogFormat {
     RON,
 }
 
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFlush {
+    Inherit,
+    EveryEntry,
+    Interval(f32),
+}
+
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogCollision {
+    Inherit,
+    Abort,
+    Suffix,
+}
+
 impl Default for LogFormat {
     #[inline(always)]
     fn default() -> Self {
@@ -63,6 +93,49 @@ impl LogFormat {
     }
 }
 
+impl Default for LogFlush {
+    #[inline(always)]
+    fn default() -> Self {
+        LogFlush::Inherit
+    }
+}
+
+impl LogFlush {
+    pub fn or(&self, other: &Self) -> Self {
+        if let Self::Inherit = self {
+            *other
+        } else {
+            *self
+        }
+    }
+
+    pub fn verify(&self) -> Result<()> {
+        match self {
+            &Self::Interval(x) if !x.is_finite() || x <= 0.0 => Err(eyre!(
+                "Config `log_flush` should have a positive interval in seconds ({x})."
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for LogCollision {
+    #[inline(always)]
+    fn default() -> Self {
+        LogCollision::Inherit
+    }
+}
+
+impl LogCollision {
+    pub fn or(&self, other: &Self) -> Self {
+        if let Self::Inherit = self {
+            *other
+        } else {
+            *self
+        }
+    }
+}
+
 impl LoggerSignal {
     #[inline(always)]
     fn requires_flush(&self) -> bool {
@@ -81,43 +154,39 @@ impl From<LoggerSignal> for AsyncSignal {
 
 impl Logger {
     pub fn new(info: &Info, config: &Config) -> Result<Self> {
-        let block = normalized_name(info.block());
-        let date = Local::now().format("%F").to_string();
-        let time = Local::now().format("%T").to_string().replace(':', "-");
-        let out_dir = info.output().join(format!("{date}/{block}/{time}"));
-
-        if out_dir.exists() {
-            return Err(eyre!("Output directory already exists: {out_dir:?}"));
-        }
+        let out_dir = output_dir(info, config)?;
         create_dir_all(&out_dir)
             .wrap_err_with(|| format!("Failed to create output directory: {out_dir:?}"))?;
 
         Ok(Self {
+            info: info.clone(),
             out_dir,
             content: HashMap::new(),
             needs_flush: false,
             log_format: config.log_format(),
+            log_flush: config.log_flush(),
+            trace: None,
+            #[cfg(feature = "rodio")]
+            capture: None,
         })
     }
 
     fn append(&mut self, time: DateTime<Local>, group: String, entry: (String, Value)) {
         let time = time.to_string();
         let (name, value) = entry;
-        let (vec, flush) = self.content.entry(group).or_default();
+        let (vec, _) = self.content.entry(group).or_default();
         vec.push((time, name, value));
-        *flush = true;
         self.needs_flush = true;
     }
 
     fn extend(&mut self, time: DateTime<Local>, group: String, entries: Vec<(String, Value)>) {
         let time = time.to_string();
-        let (vec, flush) = self.content.entry(group).or_default();
+        let (vec, _) = self.content.entry(group).or_default();
         vec.extend(
             entries
                 .into_iter()
                 .map(|(name, value)| (time.clone(), name, value)),
         );
-        *flush = true;
         self.needs_flush = true;
     }
 
@@ -140,18 +209,48 @@ impl Logger {
         Ok(())
     }
 
+    fn trace(&mut self, time: DateTime<Local>, entry: TraceEntry) -> Result<()> {
+        if self.trace.is_none() {
+            let path = self.out_dir.join(TRACE_FILE);
+            let file = File::create(&path)
+                .wrap_err_with(|| format!("Failed to create trace file ({path:?})."))?;
+            self.trace = Some(BufWriter::new(file));
+        }
+
+        serde_cbor::to_writer(
+            self.trace.as_mut().unwrap(),
+            &(time.timestamp_micros(), entry),
+        )
+        .wrap_err("Failed to write to trace file.")
+    }
+
     fn flush(&mut self) -> Result<()> {
-        for (group, (vec, flush)) in self.content.iter_mut().filter(|(_, (_, flush))| *flush) {
+        if let Some(trace) = self.trace.as_mut() {
+            trace.flush().wrap_err("Failed to flush trace file.")?;
+        }
+
+        // Only entries added since the last flush are written, appended to the existing file
+        let pending = self
+            .content
+            .iter_mut()
+            .filter(|(_, (vec, written))| *written < vec.len());
+        for (group, (vec, written)) in pending {
             let name = format!("{}.log", normalized_name(group));
             let path = self.out_dir.join(name);
-            let mut file = File::create(&path)
-                .wrap_err_with(|| format!("Failed to create log file ({path:?})."))?;
-
-            write_vec(&mut file, self.log_format, vec)?;
-            *flush = false;
 
-            #[cfg(debug_assertions)]
-            println!("{:?} -> Wrote to file: {path:?}", Local::now());
+            if *written == 0 {
+                let file = File::create(&path)
+                    .wrap_err_with(|| format!("Failed to create log file ({path:?})."))?;
+                write_vec(file, self.log_format, vec)?;
+            } else {
+                let file = OpenOptions::new()
+                    .write(true)
+                    .open(&path)
+                    .wrap_err_with(|| format!("Failed to open log file ({path:?})."))?;
+                append_vec(file, self.log_format, &vec[*written..])
+                    .wrap_err_with(|| format!("Failed to append to log file ({path:?})."))?;
+            }
+            *written = vec.len();
         }
         self.needs_flush = false;
         Ok(())
@@ -163,12 +262,14 @@ impl Logger {
         signal: LoggerSignal,
         async_writer: &QWriter<AsyncSignal>,
     ) -> Result<()> {
-        if signal.requires_flush() && !self.needs_flush {
-            let mut async_writer = async_writer.clone();
-            thread::spawn(move || {
-                thread::sleep(Duration::from_secs(5));
-                async_writer.push(LoggerSignal::Flush);
-            });
+        if let LogFlush::Interval(interval) = self.log_flush {
+            if signal.requires_flush() && !self.needs_flush {
+                let mut async_writer = async_writer.clone();
+                thread::spawn(move || {
+                    thread::sleep(Duration::from_secs_f32(interval));
+                    async_writer.push(LoggerSignal::Flush);
+                });
+            }
         }
 
         match signal {
@@ -181,24 +282,102 @@ impl Logger {
             LoggerSignal::Write(name, content) => {
                 self.write(name, content)?;
             }
+            LoggerSignal::Trace(entry) => {
+                self.trace(time, entry)?;
+            }
+            #[cfg(feature = "rodio")]
+            LoggerSignal::Capture(capture) => {
+                self.capture = Some(capture);
+                self.append(
+                    time,
+                    "main".to_owned(),
+                    ("audio_capture".to_owned(), Value::Text("start".to_owned())),
+                );
+            }
             LoggerSignal::Flush => {
                 self.flush()?;
                 self.needs_flush = false;
             }
         }
 
+        if let LogFlush::EveryEntry = self.log_flush {
+            if self.needs_flush {
+                self.flush()?;
+            }
+        }
+
         Ok(())
     }
 
     pub fn finish(&mut self) -> Result<()> {
+        // A failed capture is logged rather than aborting, so the outputs still get a manifest
+        #[cfg(feature = "rodio")]
+        if let Some(capture) = self.capture.take() {
+            if let Err(e) = capture.write(&self.out_dir.join(CAPTURE_FILE)) {
+                eprintln!("WARNING: Failed to write captured audio:\n{e:?}");
+                self.append(
+                    Local::now(),
+                    "main".to_owned(),
+                    (
+                        "audio_capture".to_owned(),
+                        Value::Text(format!("error: {e:#}")),
+                    ),
+                );
+            }
+        }
+
         self.flush()
             .wrap_err("Failed to graciously close logger.")?;
 
+        Manifest::new(&self.info, &self.out_dir)
+            .and_then(|m| m.write(&self.out_dir))
+            .wrap_err("Failed to write output manifest.")?;
+
         self.content.clear();
+        self.trace = None;
         Ok(())
     }
 }
 
+/// Fills the `output_path` template with information about the current run. If the
+/// resulting directory already exists, it is suffixed or rejected based on `log_collision`.
+fn output_dir(info: &Info, config: &Config) -> Result<PathBuf> {
+    let now = Local::now();
+    let template = config
+        .output_path()
+        .replace("{subject}", info.subject())
+        .replace("{task}", &normalized_name(info.task()))
+        .replace("{block}", &normalized_name(info.block()))
+        .replace("{session}", info.session())
+        .replace("{date}", &now.format("%F").to_string())
+        .replace("{time}", &now.format("%T").to_string().replace(':', "-"));
+
+    let mut run = 1;
+    let mut out_dir = info.output().join(template.replace("{run}", "1"));
+    while template.contains("{run}") && out_dir.exists() {
+        run += 1;
+        out_dir = info
+            .output()
+            .join(template.replace("{run}", &run.to_string()));
+    }
+
+    if out_dir.exists() {
+        match config.log_collision() {
+            LogCollision::Suffix => {
+                let mut suffix = 2;
+                let base = out_dir.clone();
+                while out_dir.exists() {
+                    out_dir = PathBuf::from(format!("{}_{suffix}", base.display()));
+                    suffix += 1;
+                }
+            }
+            _ => return Err(eyre!("Output directory already exists: {out_dir:?}")),
+        }
+    }
+
+    Ok(out_dir)
+}
+
 pub fn normalized_name(name: &str) -> String {
     name.to_lowercase()
         .split_whitespace()
@@ -206,7 +385,27 @@ pub fn normalized_name(name: &str) -> String {
         .replace('-', "_")
 }
 
-fn write_vec(file: &mut File, fmt: LogFormat, vec: &Vec<(String, String, Value)>) -> Result<()> {
+pub fn read_log(path: &Path) -> Result<Vec<(String, String, Value)>> {
+    let content = fs::read_to_string(path)
+        .wrap_err_with(|| format!("Failed to read log file ({path:?})."))?;
+
+    if let Ok(vec) = serde_json::from_str(&content) {
+        Ok(vec)
+    } else if let Ok(vec) = ron::from_str(&content) {
+        Ok(vec)
+    } else {
+        serde_yaml::from_str(&content)
+            .wrap_err_with(|| format!("Failed to parse log file ({path:?})."))
+    }
+}
+
+#[inline(always)]
+pub fn parse_log_time(time: &str) -> Result<DateTime<FixedOffset>> {
+    DateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S%.f %:z")
+        .wrap_err_with(|| format!("Failed to parse log timestamp ({time})."))
+}
+
+fn write_vec<W: Write>(file: W, fmt: LogFormat, vec: &[(String, String, Value)]) -> Result<()> {
     let mut vec_t: Vec<(&str, &str, Serializable)> = vec![];
     for (a, b, v) in vec {
         vec_t.push((a, b, Serializable::try_from(v)?));
@@ -215,6 +414,31 @@ fn write_vec(file: &mut File, fmt: LogFormat, vec: &Vec<(String, String, Value)>
     write_as(file, &vec_t, fmt)
 }
 
+/// Appends entries to a log file written by `write_vec`, by continuing its sequence where the
+/// latter closed it, so that the file remains a single valid document.
+fn append_vec(mut file: File, fmt: LogFormat, vec: &[(String, String, Value)]) -> Result<()> {
+    // Closing of the sequence in the file, separator from the entries before, and opening of
+    // the sequence of new entries, as formatted by `write_as`
+    let (closing, separator, opening) = match fmt {
+        LogFormat::Inherit => return Err(eyre!("Cannot log with log_format=`Inherit`.")),
+        LogFormat::JSON => ("\n]", ",", "["),
+        LogFormat::YAML => ("", "", ""),
+        LogFormat::RON => ("]", "", "[\n"),
+    };
+
+    let mut entries = vec![];
+    write_vec(&mut entries, fmt, vec)?;
+    let entries = entries
+        .strip_prefix(opening.as_bytes())
+        .ok_or_else(|| eyre!("Unexpected formatting of log entries."))?;
+
+    file.seek(SeekFrom::End(-(closing.len() as i64)))
+        .wrap_err("Failed to seek to the end of the log.")?;
+    file.write_all(separator.as_bytes())
+        .and_then(|_| file.write_all(entries))
+        .wrap_err("Failed to write log entries.")
+}
+
 fn write_as<W, T>(mut file: W, content: &T, fmt: LogFormat) -> Result<()>
 where
     W: Write,
@@ -0,0 +1,218 @@
+use crate::server::Info;
+use crate::util::{file_hash, Hash};
+use chrono::{DateTime, FixedOffset, Local};
+use eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Manifest {
+    time: String,
+    info: Info,
+    files: BTreeMap<String, String>,
+    previous: Option<ManifestLink>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ManifestLink {
+    path: PathBuf,
+    hash: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SignedManifest {
+    #[serde(flatten)]
+    manifest: Manifest,
+    hash: String,
+}
+
+impl Hash for Manifest {}
+
+impl Manifest {
+    /// Creates a manifest of all files in `out_dir`, chained to the latest manifest found
+    /// under the subject's output directory.
+    pub fn new(info: &Info, out_dir: &Path) -> Result<Self> {
+        let mut files = BTreeMap::new();
+        for entry in fs::read_dir(out_dir)
+            .wrap_err_with(|| format!("Failed to read output directory ({out_dir:?})."))?
+        {
+            let path = entry?.path();
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            if path.is_file() && name != MANIFEST_FILE {
+                files.insert(name, file_hash(&path)?);
+            }
+        }
+
+        let subject_dir = info.output();
+        let previous = latest_manifest(subject_dir)?.map(|(path, signed)| ManifestLink {
+            path: path.strip_prefix(subject_dir).unwrap_or(&path).to_owned(),
+            hash: signed.hash,
+        });
+
+        Ok(Self {
+            time: Local::now().to_rfc3339(),
+            info: info.clone(),
+            files,
+            previous,
+        })
+    }
+
+    pub fn write(self, out_dir: &Path) -> Result<()> {
+        let path = out_dir.join(MANIFEST_FILE);
+        let signed = SignedManifest {
+            hash: self.hash(),
+            manifest: self,
+        };
+
+        fs::write(
+            &path,
+            serde_json::to_string_pretty(&signed).wrap_err("Failed to serialize manifest.")?,
+        )
+        .wrap_err_with(|| format!("Failed to write manifest file ({path:?})."))
+    }
+
+    #[inline(always)]
+    pub fn info(&self) -> &Info {
+        &self.info
+    }
+
+    #[inline(always)]
+    pub fn time(&self) -> Result<DateTime<FixedOffset>> {
+        DateTime::parse_from_rfc3339(&self.time)
+            .wrap_err_with(|| format!("Failed to parse manifest timestamp ({}).", self.time))
+    }
+}
+
+impl SignedManifest {
+    pub fn read(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .wrap_err_with(|| format!("Failed to read manifest file ({path:?})."))?;
+        serde_json::from_str(&content)
+            .wrap_err_with(|| format!("Failed to parse manifest file ({path:?})."))
+    }
+
+    #[inline(always)]
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+}
+
+/// Verifies all block runs recorded under a subject's output directory. Returns a list of
+/// detected problems, which is empty if all data files are intact.
+pub fn verify_outputs(subject_dir: &Path) -> Result<Vec<String>> {
+    let mut issues = vec![];
+    let mut manifests = vec![];
+
+    for dir in run_dirs(subject_dir)? {
+        let path = dir.join(MANIFEST_FILE);
+        if !path.exists() {
+            issues.push(format!("Missing manifest in output directory: {dir:?}"));
+            continue;
+        }
+
+        let signed = match SignedManifest::read(&path) {
+            Ok(signed) => signed,
+            Err(e) => {
+                issues.push(format!("{e:#}"));
+                continue;
+            }
+        };
+
+        if signed.manifest.hash() != signed.hash {
+            issues.push(format!("Manifest has been modified: {path:?}"));
+        }
+
+        for (name, hash) in signed.manifest.files.iter() {
+            let file = dir.join(name);
+            if !file.exists() {
+                issues.push(format!("Missing data file: {file:?}"));
+            } else if &file_hash(&file)? != hash {
+                issues.push(format!("Data file has been modified: {file:?}"));
+            }
+        }
+
+        for entry in fs::read_dir(&dir)? {
+            let file = entry?.path();
+            let name = file.file_name().unwrap().to_string_lossy().to_string();
+            if file.is_file() && name != MANIFEST_FILE && !signed.manifest.files.contains_key(&name)
+            {
+                issues.push(format!("Data file is not listed in manifest: {file:?}"));
+            }
+        }
+
+        match signed.manifest.time() {
+            Ok(time) => manifests.push((time, path, signed)),
+            Err(e) => issues.push(format!("{e:#}")),
+        }
+    }
+
+    manifests.sort_by_key(|(time, _, _)| *time);
+    let mut previous: Option<ManifestLink> = None;
+    for (_, path, signed) in manifests {
+        if signed.manifest.previous != previous {
+            issues.push(format!(
+                "Broken manifest chain (expected previous: {previous:?}): {path:?}"
+            ));
+        }
+
+        previous = Some(ManifestLink {
+            path: path.strip_prefix(subject_dir).unwrap_or(&path).to_owned(),
+            hash: signed.hash,
+        });
+    }
+
+    Ok(issues)
+}
+
+fn latest_manifest(subject_dir: &Path) -> Result<Option<(PathBuf, SignedManifest)>> {
+    let mut latest: Option<(DateTime<FixedOffset>, PathBuf, SignedManifest)> = None;
+    for dir in run_dirs(subject_dir)? {
+        let path = dir.join(MANIFEST_FILE);
+        if !path.exists() {
+            continue;
+        }
+
+        // Unreadable manifests are reported by `verify_outputs` rather than breaking the chain
+        let (signed, time) = match SignedManifest::read(&path)
+            .and_then(|signed| signed.manifest.time().map(|time| (signed, time)))
+        {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                println!("WARNING: Skipping unreadable manifest:\n{e:#}");
+                continue;
+            }
+        };
+        if !matches!(&latest, Some((t, _, _)) if *t >= time) {
+            latest = Some((time, path, signed));
+        }
+    }
+
+    Ok(latest.map(|(_, path, signed)| (path, signed)))
+}
+
+/// Recursively finds all block run directories (those containing a `main.log`).
+pub fn run_dirs(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut dirs = vec![];
+    if !dir.is_dir() {
+        return Ok(dirs);
+    }
+
+    if dir.join("main.log").exists() {
+        dirs.push(dir.to_owned());
+    }
+
+    for entry in
+        fs::read_dir(dir).wrap_err_with(|| format!("Failed to read output directory ({dir:?})."))?
+    {
+        let path = entry?.path();
+        if path.is_dir() {
+            dirs.extend(run_dirs(&path)?);
+        }
+    }
+
+    Ok(dirs)
+}
@@ -1,26 +1,34 @@
 pub mod address;
 pub mod audio;
+pub mod bids;
 pub mod color;
 pub mod function;
 pub mod image;
 pub mod key;
+pub mod lock;
 pub mod logger;
+pub mod manifest;
 pub mod optional;
 pub mod stream;
 pub mod text;
+pub mod trace;
 pub mod value;
 
 pub use crate::resource::image::*;
 pub use address::*;
 pub use audio::*;
+pub use bids::*;
 pub use color::*;
 pub use function::*;
 pub use key::*;
+pub use lock::*;
 pub use logger::*;
+pub use manifest::*;
 pub use mask::*;
 pub use optional::*;
 pub use stream::*;
 pub use text::*;
+pub use trace::*;
 pub use value::*;
 
 use crate::assets::{IMAGE_FIXATION, IMAGE_RUSTACEAN};
@@ -61,6 +69,9 @@ impl ResourceManager {
         // Clean up existing resource map
         map.clear();
 
+        // Load resource lockfile if it exists
+        let lock = ResourceLock::load(env.task())?;
+
         // Load default fixation image
         map.entry(ResourceAddr::Image("fixation.svg".into()))
             .or_insert({
@@ -99,6 +110,14 @@ impl ResourceManager {
             }
 
             if is_new {
+                if let Some(lock) = lock.as_ref() {
+                    lock.verify(
+                        src.path(),
+                        src.prefix(env.resource()).path(),
+                        config.resource_lock(),
+                    )?;
+                }
+
                 let data: ResourceValue = match src.prefix(env.resource()) {
                     ResourceAddr::Ref(path) => path.into(),
                     ResourceAddr::Text(path) => std::fs::read_to_string(&path)
@@ -122,6 +141,10 @@ impl ResourceManager {
                         .wrap_err_with(|| eyre!("Failed to load audio resource ({path:?})"))?
                         .with_direction(channel)?
                         .into(),
+                    ResourceAddr::Synth(synth, channel) => audio_from_synth(&synth, config)
+                        .wrap_err_with(|| eyre!("Failed to synthesize audio resource ({synth:?})"))?
+                        .with_direction(channel)?
+                        .into(),
                     ResourceAddr::Video(path) => {
                         video_from_file(tex_manager.clone(), &path, config)
                             .wrap_err_with(|| eyre!("Failed to load video resource ({path:?})"))?
@@ -172,4 +195,22 @@ impl IoManager {
     pub fn audio(&self) -> Result<AudioSink> {
         self.audio.sink()
     }
+
+    /// Whether audio is played at all (i.e. the audio backend is not `none`).
+    #[inline(always)]
+    pub fn has_audio(&self) -> bool {
+        !matches!(self.audio, AudioDevice::None)
+    }
+
+    /// Number of output channels of the audio device, if it has a fixed layout.
+    #[inline(always)]
+    pub fn audio_channels(&self) -> Option<u16> {
+        self.audio.output_channels()
+    }
+
+    #[cfg(feature = "rodio")]
+    #[inline(always)]
+    pub fn capture(&self) -> Option<Capture> {
+        self.audio.capture()
+    }
 }
@@ -1,28 +1,36 @@
-use crate::resource::{FrameBuffer, MediaStream, StreamMode};
+use crate::resource::{AudioChannel, AudioSink, FrameBuffer, IoManager, MediaStream, StreamMode};
 use crate::server::Config;
 use crate::util::spin_sleeper;
 use eframe::egui::mutex::RwLock;
 use eframe::egui::{ColorImage, ImageData, TextureId, TextureOptions, Vec2};
 use eframe::epaint::TextureManager;
 use eyre::{eyre, Context as _, Result};
-use ffmpeg::format::{context::Input, input, Pixel};
+use ffmpeg::format::{input, sample, Pixel, Sample};
 use ffmpeg::media::Type;
+use ffmpeg::software::resampling;
 use ffmpeg::software::scaling::{context::Context, flag::Flags};
+use ffmpeg::util::frame::audio::Audio;
 use ffmpeg::util::frame::video::Video;
+use ffmpeg::ChannelLayout;
 use ffmpeg_next as ffmpeg;
 use once_cell::sync::OnceCell;
+use spin_sleep::SpinSleeper;
 use std::path::{Path, PathBuf};
-use std::sync::mpsc::Sender;
-use std::sync::{mpsc, Arc, Mutex};
+use std::sync::{Arc, Mutex, Weak};
 use std::thread;
 use std::time::{Duration, Instant};
 
 static FFMPEG_INIT: OnceCell<()> = OnceCell::new();
 
+/// How far ahead of the playback clock decoded audio is queued on the sink.
+const AUDIO_LEAD: Duration = Duration::from_secs(1);
+
+/// Polling interval of decoder threads while the stream is paused or waiting to restart.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
 #[derive(Clone)]
 pub struct Stream {
     path: PathBuf,
-    _context: Option<Arc<Mutex<Input>>>,
     video_index: Option<usize>,
     audio_index: Option<usize>,
     frame_size: [u32; 2],
@@ -30,12 +38,31 @@ pub struct Stream {
     audio_chan: u16,
     audio_rate: u32,
     duration: Duration,
-    is_eos: Arc<Mutex<bool>>,
-    paused: bool,
-    starter: Option<Sender<()>>,
+    is_eos: bool,
+    volume: f32,
+    muted: bool,
+    clock: Arc<Mutex<Clock>>,
+    sink: Option<Arc<Mutex<AudioSink>>>,
     tex_manager: Arc<RwLock<TextureManager>>,
 }
 
+/// Playback clock shared by a stream and its decoder threads. Decoder threads only hold a weak
+/// reference to it, so they exit once the stream is dropped.
+struct Clock {
+    origin: Option<Instant>,
+    paused_at: Option<Instant>,
+    generation: u64,
+    decoders: usize,
+    finished: usize,
+    error: Option<eyre::Report>,
+}
+
+enum Wait {
+    Ready,
+    Restarted(u64),
+    Dropped,
+}
+
 impl MediaStream for Stream {
     fn new(
         tex_manager: Arc<RwLock<TextureManager>>,
@@ -78,7 +105,6 @@ impl MediaStream for Stream {
 
         Ok(Stream {
             path: path.to_owned(),
-            _context: None,
             video_index,
             audio_index,
             frame_size: [width, height],
@@ -86,9 +112,11 @@ impl MediaStream for Stream {
             audio_chan,
             audio_rate,
             duration,
-            is_eos: Arc::new(Mutex::new(false)),
-            paused: true,
-            starter: None,
+            is_eos: false,
+            volume: 1.0,
+            muted: false,
+            clock: Arc::new(Mutex::new(Clock::new(0))),
+            sink: None,
             tex_manager,
         })
     }
@@ -96,131 +124,63 @@ impl MediaStream for Stream {
     fn cloned(
         &self,
         frame: Arc<Mutex<Option<(TextureId, Vec2)>>>,
-        _media_mode: StreamMode,
-        _volume: f32,
+        media_mode: StreamMode,
+        volume: f32,
+        io: &IoManager,
     ) -> Result<Self> {
-        let context = input(&self.path)?;
-        // context
-        //     .pause()
-        //     .map_err(|e| InternalError(format!("{e:#?}")))?;
+        let channel = match media_mode {
+            // Without an audio backend, streams are played without sound
+            StreamMode::Normal(channel) if self.audio_index.is_some() && io.has_audio() => {
+                Some(channel)
+            }
+            _ => None,
+        };
 
-        let context = Arc::new(Mutex::new(context));
-        let is_eos = Arc::new(Mutex::new(*self.is_eos.lock().unwrap()));
-        let (tx_start, rx_start) = mpsc::channel();
+        // Audio is held back until the stream is started
+        let sink = if channel.is_some() {
+            let mut sink = io.audio()?;
+            sink.pause()?;
+            sink.set_volume(volume)?;
+            Some(Arc::new(Mutex::new(sink)))
+        } else {
+            None
+        };
+
+        let decoders = self.video_index.iter().count() + sink.iter().count();
+        let clock = Arc::new(Mutex::new(Clock::new(decoders)));
 
         if let Some(index) = self.video_index {
             let path = self.path.clone();
-            let framerate = self.frame_rate;
-            let context = context.clone();
             let tex_manager = self.tex_manager.clone();
-            let is_eos = is_eos.clone();
+            let clock = Arc::downgrade(&clock);
 
             thread::spawn(move || {
-                let (mut decoder, mut scaler) = {
-                    let context = context.lock().unwrap();
-                    let stream = context.stream(index).expect("Failed to fetch video stream");
-
-                    let decoder =
-                        ffmpeg::codec::context::Context::from_parameters(stream.parameters())
-                            .expect("Failed to create context for video stream")
-                            .decoder()
-                            .video()
-                            .expect("Failed to decode video stream");
-
-                    let scaler = Context::get(
-                        decoder.format(),
-                        decoder.width(),
-                        decoder.height(),
-                        Pixel::RGBA,
-                        decoder.width(),
-                        decoder.height(),
-                        Flags::BILINEAR,
-                    )
-                    .expect("Failed to get context for decoded video stream");
-
-                    (decoder, scaler)
-                };
-
-                let sleeper = spin_sleeper();
-
-                if rx_start.recv().is_err() {
-                    *is_eos.lock().unwrap() = true;
-                    return;
+                if let Err(e) = decode_video(&path, index, frame, tex_manager, &clock) {
+                    report(
+                        &clock,
+                        e.wrap_err(format!("Failed to decode video: {path:?}")),
+                    );
                 }
+            });
+        }
 
-                let dt = Duration::from_secs_f64(1.0 / framerate);
-                let mut frame_start;
-
-                loop {
-                    frame_start = Instant::now();
-
-                    {
-                        let mut context = context.lock().unwrap();
-                        let (stream, packet) = match context.packets().next() {
-                            Some(packet) => packet,
-                            None => break,
-                        };
-                        if stream.index() != index {
-                            continue;
-                        }
-
-                        decoder
-                            .send_packet(&packet)
-                            .expect("Failed to send ffmpeg packet");
-
-                        let mut decoded = Video::empty();
-                        while decoder.receive_frame(&mut decoded).is_ok() {
-                            let mut rgba_frame = Video::empty();
-                            scaler
-                                .run(&decoded, &mut rgba_frame)
-                                .expect("Failed to run scaler");
-                            *frame.lock().unwrap() = Some((
-                                tex_manager.write().alloc(
-                                    format!("{path:?}:@:[current]"),
-                                    ImageData::Color(ColorImage::from_rgba_unmultiplied(
-                                        [rgba_frame.width() as _, rgba_frame.height() as _],
-                                        rgba_frame.data(0),
-                                    )),
-                                    TextureOptions::LINEAR,
-                                ),
-                                Vec2::new(rgba_frame.width() as _, rgba_frame.height() as _),
-                            ));
-                        }
-                    }
-
-                    let now = Instant::now();
-                    sleeper.sleep(frame_start + dt - now);
-                }
+        if let (Some(index), Some(channel), Some(sink)) = (self.audio_index, channel, &sink) {
+            let path = self.path.clone();
+            let sink = sink.clone();
+            let clock = Arc::downgrade(&clock);
 
-                *is_eos.lock().unwrap() = true;
-                decoder
-                    .send_eof()
-                    .wrap_err("Failed to send EOF to decoder.")
-                    .unwrap();
+            thread::spawn(move || {
+                if let Err(e) = decode_audio(&path, index, channel, sink, &clock) {
+                    report(
+                        &clock,
+                        e.wrap_err(format!("Failed to decode audio: {path:?}")),
+                    );
+                }
             });
         }
 
-        // if let Some(index) = self.video_index {
-        //     let stream = context.stream(index).ok_or(InternalError(format!(
-        //         "Failed to fetch audio stream for: {:?}",
-        //         self.path
-        //     )))?;
-        //
-        //     let mut decoder =
-        //         ffmpeg::codec::context::Context::from_parameters(stream.parameters())?
-        //             .decoder()
-        //             .audio()
-        //             .map_err(|e| {
-        //                 InternalError(format!(
-        //                     "Failed to decode audio stream for: {:?}",
-        //                     self.path
-        //                 ))
-        //             })?;
-        // }
-
         Ok(Stream {
             path: self.path.clone(),
-            _context: Some(context),
             video_index: self.video_index,
             audio_index: self.audio_index,
             frame_size: self.frame_size,
@@ -228,15 +188,17 @@ impl MediaStream for Stream {
             audio_chan: self.audio_chan,
             audio_rate: self.audio_rate,
             duration: self.duration,
-            is_eos,
-            paused: self.paused,
-            starter: Some(tx_start),
+            is_eos: false,
+            volume,
+            muted: false,
+            clock,
+            sink,
             tex_manager: self.tex_manager.clone(),
         })
     }
 
     fn eos(&self) -> bool {
-        *self.is_eos.lock().unwrap()
+        self.is_eos
     }
 
     fn size(&self) -> [u32; 2] {
@@ -264,48 +226,27 @@ impl MediaStream for Stream {
     }
 
     fn start(&mut self) -> Result<()> {
-        // self.context
-        //     .as_ref()
-        //     .unwrap()
-        //     .lock()
-        //     .unwrap()
-        //     .play()
-        //     .map_err(|e| {
-        //         VideoDecodingError(format!(
-        //             "Failed to start stream for: {:?}\n{e:#?}",
-        //             self.path
-        //         ))
-        //     })?;
-        self.paused = false;
-        if let Some(link) = self.starter.take() {
-            link.send(())
-                .wrap_err("Failed to start ffmpeg parallel thread.")
-        } else {
-            Err(eyre!("Tried to start ffmpeg parallel thread twice."))
-        }
+        self.set_paused(false)
     }
 
     fn restart(&mut self) -> Result<()> {
-        // self.context.as_ref().unwrap().lock().unwrap().seek(0);
+        {
+            let mut clock = self.clock.lock().unwrap();
+            clock.origin = Some(Instant::now());
+            clock.paused_at = None;
+            clock.generation += 1;
+            clock.finished = 0;
+        }
+
+        self.is_eos = false;
+        if let Some(sink) = self.sink.as_ref() {
+            sink.lock().unwrap().play()?;
+        }
         Ok(())
     }
 
     fn pause(&mut self) -> Result<()> {
-        // self.context
-        //     .as_ref()
-        //     .unwrap()
-        //     .lock()
-        //     .unwrap()
-        //     .pause()
-        //     .map_err(|e| {
-        //         VideoDecodingError(format!(
-        //             "Failed to pause stream for: {:?}\n{e:#?}",
-        //             self.path
-        //         ))
-        //     })?;
-        self.paused = true;
-        self._context.take();
-        Ok(())
+        self.set_paused(true)
     }
 
     fn pull_samples(&self) -> Result<(FrameBuffer, f64)> {
@@ -376,11 +317,383 @@ impl MediaStream for Stream {
         Ok((Arc::new(frames), self.frame_rate))
     }
 
-    fn process_bus(&mut self, _looping: bool) -> Result<bool> {
-        Ok(self.eos())
+    fn process_bus(&mut self, looping: bool) -> Result<bool> {
+        let (finished, error) = {
+            let mut clock = self.clock.lock().unwrap();
+            (clock.finished >= clock.decoders, clock.error.take())
+        };
+
+        if let Some(e) = error {
+            return Err(e);
+        }
+        if !finished || self.is_eos {
+            return Ok(self.is_eos);
+        }
+
+        if looping {
+            self.restart()?;
+            Ok(false)
+        } else {
+            self.is_eos = true;
+            self.set_paused(true)?;
+            Ok(true)
+        }
+    }
+}
+
+impl Stream {
+    /// Set the volume multiplier of the audio.
+    /// `0.0` = 0% volume, `1.0` = 100% volume.
+    pub fn set_volume(&mut self, volume: f64) -> Result<()> {
+        self.volume = volume as f32;
+        self.update_volume()
+    }
+
+    /// Set if the audio is muted or not, without changing the volume.
+    pub fn set_muted(&mut self, muted: bool) -> Result<()> {
+        self.muted = muted;
+        self.update_volume()
+    }
+
+    fn update_volume(&mut self) -> Result<()> {
+        if let Some(sink) = self.sink.as_ref() {
+            let volume = if self.muted { 0.0 } else { self.volume };
+            sink.lock().unwrap().set_volume(volume)?;
+        }
+        Ok(())
+    }
+
+    /// Set if the media is paused or not. The sink is locked first, in the same order as the
+    /// audio thread, so audio is never queued between the two.
+    fn set_paused(&mut self, paused: bool) -> Result<()> {
+        let mut sink = self.sink.as_ref().map(|s| s.lock().unwrap());
+
+        {
+            let mut clock = self.clock.lock().unwrap();
+            let now = Instant::now();
+            match (clock.origin, clock.paused_at, paused) {
+                (None, _, false) => clock.origin = Some(now),
+                (Some(origin), Some(paused_at), false) => {
+                    clock.origin = Some(origin + (now - paused_at));
+                    clock.paused_at = None;
+                }
+                (Some(_), None, true) => clock.paused_at = Some(now),
+                _ => {}
+            }
+        }
+
+        match (sink.as_mut(), paused) {
+            (Some(sink), false) => sink.play(),
+            (Some(sink), true) => sink.pause(),
+            (None, _) => Ok(()),
+        }
+    }
+}
+
+impl Clock {
+    fn new(decoders: usize) -> Self {
+        Self {
+            origin: None,
+            paused_at: None,
+            generation: 0,
+            decoders,
+            finished: 0,
+            error: None,
+        }
+    }
+
+    /// Playback time of the current generation, which does not advance while paused.
+    fn elapsed(&self) -> Duration {
+        match (self.origin, self.paused_at) {
+            (None, _) => Duration::default(),
+            (Some(origin), Some(paused_at)) => paused_at.saturating_duration_since(origin),
+            (Some(origin), None) => origin.elapsed(),
+        }
+    }
+
+    #[inline(always)]
+    fn running(&self) -> bool {
+        self.origin.is_some() && self.paused_at.is_none()
+    }
+}
+
+/// Blocks until playback of the given generation reaches `time`.
+fn wait_until(
+    clock: &Weak<Mutex<Clock>>,
+    generation: u64,
+    time: Duration,
+    sleeper: &SpinSleeper,
+) -> Wait {
+    loop {
+        let (remaining, running) = match clock.upgrade() {
+            Some(clock) => {
+                let clock = clock.lock().unwrap();
+                if clock.generation != generation {
+                    return Wait::Restarted(clock.generation);
+                }
+                (time.saturating_sub(clock.elapsed()), clock.running())
+            }
+            None => return Wait::Dropped,
+        };
+
+        match (remaining.is_zero(), running) {
+            (true, _) => return Wait::Ready,
+            (false, true) => sleeper.sleep(remaining.min(POLL_INTERVAL)),
+            (false, false) => sleeper.sleep(POLL_INTERVAL),
+        }
+    }
+}
+
+/// Marks a decoder as done with the given generation and blocks until the stream restarts.
+fn wait_restart(clock: &Weak<Mutex<Clock>>, generation: u64, sleeper: &SpinSleeper) -> Wait {
+    if let Some(clock) = clock.upgrade() {
+        clock.lock().unwrap().finished += 1;
+    }
+
+    loop {
+        match clock.upgrade() {
+            Some(clock) => {
+                let clock = clock.lock().unwrap();
+                if clock.generation != generation {
+                    return Wait::Restarted(clock.generation);
+                }
+            }
+            None => return Wait::Dropped,
+        }
+        sleeper.sleep(POLL_INTERVAL);
     }
 }
 
+fn report(clock: &Weak<Mutex<Clock>>, error: eyre::Report) {
+    if let Some(clock) = clock.upgrade() {
+        clock.lock().unwrap().error.get_or_insert(error);
+    }
+}
+
+/// Decodes the video stream and displays each frame once the playback clock reaches its
+/// presentation time.
+fn decode_video(
+    path: &Path,
+    index: usize,
+    frame: Arc<Mutex<Option<(TextureId, Vec2)>>>,
+    tex_manager: Arc<RwLock<TextureManager>>,
+    clock: &Weak<Mutex<Clock>>,
+) -> Result<()> {
+    let mut context = input(&path)?;
+    let (mut decoder, time_base) = {
+        let stream = context
+            .stream(index)
+            .ok_or_else(|| eyre!("Failed to fetch video stream."))?;
+        let decoder = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?
+            .decoder()
+            .video()?;
+        (decoder, f64::from(stream.time_base()))
+    };
+
+    let mut scaler = Context::get(
+        decoder.format(),
+        decoder.width(),
+        decoder.height(),
+        Pixel::RGBA,
+        decoder.width(),
+        decoder.height(),
+        Flags::BILINEAR,
+    )?;
+
+    let sleeper = spin_sleeper();
+    let mut generation = 0;
+    let mut first_pts = None;
+
+    loop {
+        let packet = match context.packets().next() {
+            Some((stream, _)) if stream.index() != index => continue,
+            Some((_, packet)) => Some(packet),
+            None => None,
+        };
+
+        match packet.as_ref() {
+            Some(packet) => decoder.send_packet(packet)?,
+            None => decoder.send_eof()?,
+        }
+
+        let mut wait = Wait::Ready;
+        let mut decoded = Video::empty();
+        while decoder.receive_frame(&mut decoded).is_ok() {
+            let pts = decoded.timestamp().unwrap_or_default();
+            let offset = (pts - *first_pts.get_or_insert(pts)) as f64 * time_base;
+            wait = wait_until(
+                clock,
+                generation,
+                Duration::from_secs_f64(offset.max(0.0)),
+                &sleeper,
+            );
+            if !matches!(wait, Wait::Ready) {
+                break;
+            }
+
+            let mut rgba_frame = Video::empty();
+            scaler.run(&decoded, &mut rgba_frame)?;
+            *frame.lock().unwrap() = Some((
+                tex_manager.write().alloc(
+                    format!("{path:?}:@:[current]"),
+                    ImageData::Color(ColorImage::from_rgba_unmultiplied(
+                        [rgba_frame.width() as _, rgba_frame.height() as _],
+                        rgba_frame.data(0),
+                    )),
+                    TextureOptions::LINEAR,
+                ),
+                Vec2::new(rgba_frame.width() as _, rgba_frame.height() as _),
+            ));
+        }
+
+        if packet.is_none() && matches!(wait, Wait::Ready) {
+            wait = wait_restart(clock, generation, &sleeper);
+        }
+
+        match wait {
+            Wait::Ready => {}
+            Wait::Restarted(g) => {
+                generation = g;
+                first_pts = None;
+                context.seek(0, ..0)?;
+                decoder.flush();
+            }
+            Wait::Dropped => return Ok(()),
+        }
+    }
+}
+
+/// Decodes the audio stream into 16-bit samples and queues them on the sink, staying at most
+/// `AUDIO_LEAD` ahead of the playback clock.
+fn decode_audio(
+    path: &Path,
+    index: usize,
+    channel: AudioChannel,
+    sink: Arc<Mutex<AudioSink>>,
+    clock: &Weak<Mutex<Clock>>,
+) -> Result<()> {
+    let mut context = input(&path)?;
+    let mut decoder = {
+        let stream = context
+            .stream(index)
+            .ok_or_else(|| eyre!("Failed to fetch audio stream."))?;
+        ffmpeg::codec::context::Context::from_parameters(stream.parameters())?
+            .decoder()
+            .audio()?
+    };
+
+    let layout = match decoder.channel_layout() {
+        layout if layout.is_empty() => ChannelLayout::default(decoder.channels() as i32),
+        layout => layout,
+    };
+    // Directional output is routed from mono audio
+    let out_layout = match channel {
+        AudioChannel::Stereo => layout,
+        AudioChannel::Left | AudioChannel::Right => ChannelLayout::MONO,
+    };
+    let channels = out_layout.channels() as u16;
+    let rate = decoder.rate();
+    let mut resampler = resampling::Context::get(
+        decoder.format(),
+        layout,
+        rate,
+        Sample::I16(sample::Type::Packed),
+        out_layout,
+        rate,
+    )?;
+
+    let sleeper = spin_sleeper();
+    let mut generation = 0;
+    let mut queued = Duration::default();
+
+    loop {
+        let packet = match context.packets().next() {
+            Some((stream, _)) if stream.index() != index => continue,
+            Some((_, packet)) => Some(packet),
+            None => None,
+        };
+
+        match packet.as_ref() {
+            Some(packet) => decoder.send_packet(packet)?,
+            None => decoder.send_eof()?,
+        }
+
+        let mut samples = vec![];
+        let mut decoded = Audio::empty();
+        while decoder.receive_frame(&mut decoded).is_ok() {
+            if decoded.channel_layout().is_empty() {
+                decoded.set_channel_layout(layout);
+            }
+            let mut resampled = Audio::empty();
+            resampler.run(&decoded, &mut resampled)?;
+            samples.extend(interleaved(&resampled, channels));
+        }
+        if packet.is_none() {
+            let mut resampled = Audio::empty();
+            resampler.flush(&mut resampled)?;
+            samples.extend(interleaved(&resampled, channels));
+        }
+
+        let mut wait = Wait::Ready;
+        if !samples.is_empty() {
+            wait = wait_until(
+                clock,
+                generation,
+                queued.saturating_sub(AUDIO_LEAD),
+                &sleeper,
+            );
+            if let Wait::Ready = wait {
+                queued +=
+                    Duration::from_secs_f64(samples.len() as f64 / (channels as f64 * rate as f64));
+
+                let mut sink = sink.lock().unwrap();
+                let buffer = sink
+                    .buffer(channels, rate, samples)?
+                    .with_direction(channel)?;
+                sink.queue(buffer)?;
+                let running = clock
+                    .upgrade()
+                    .map_or(false, |c| c.lock().unwrap().running());
+                if running {
+                    sink.play()?;
+                }
+            }
+        }
+
+        // The audio has ended once the playback clock has gone through all of it
+        if packet.is_none() && matches!(wait, Wait::Ready) {
+            wait = match wait_until(clock, generation, queued, &sleeper) {
+                Wait::Ready => wait_restart(clock, generation, &sleeper),
+                wait => wait,
+            };
+        }
+
+        match wait {
+            Wait::Ready => {}
+            Wait::Restarted(g) => {
+                generation = g;
+                queued = Duration::default();
+                context.seek(0, ..0)?;
+                decoder.flush();
+            }
+            Wait::Dropped => return Ok(()),
+        }
+    }
+}
+
+/// Interleaved 16-bit samples of a packed audio frame.
+fn interleaved(frame: &Audio, channels: u16) -> Vec<i16> {
+    if frame.samples() == 0 {
+        return vec![];
+    }
+
+    let len = frame.samples() * channels as usize * 2;
+    frame.data(0)[..len]
+        .chunks_exact(2)
+        .map(|b| i16::from_ne_bytes([b[0], b[1]]))
+        .collect()
+}
+
 pub fn init() -> Result<()> {
     if FFMPEG_INIT.get().is_some() {
         return Ok(());
@@ -0,0 +1,332 @@
+use crate::resource::texture::texture_from_image;
+use crate::resource::{image_from_file, FrameBuffer, IoManager, MediaStream, StreamMode};
+use crate::server::Config;
+use crate::util::spin_sleeper;
+use eframe::egui::mutex::RwLock;
+use eframe::egui::{ColorImage, TextureId, Vec2};
+use eframe::epaint::TextureManager;
+use eyre::{eyre, Context, Result};
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::AnimationDecoder;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Polling interval of the playback thread while the stream is paused.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Decoded frames, along with their sizes.
+type Frames = Vec<(ColorImage, Vec2)>;
+
+/// Plays a directory of numbered images, or an animated GIF/PNG, from frames that are all
+/// decoded up front.
+#[derive(Clone)]
+pub struct Stream {
+    path: PathBuf,
+    frames: FrameBuffer,
+    frame_size: [u32; 2],
+    frame_rate: f64,
+    schedule: Arc<Vec<Duration>>,
+    is_eos: bool,
+    clock: Arc<Mutex<Clock>>,
+}
+
+/// Playback clock shared by a stream and its playback thread.
+#[derive(Default)]
+struct Clock {
+    origin: Option<Instant>,
+    paused_at: Option<Instant>,
+}
+
+impl MediaStream for Stream {
+    fn new(tex_manager: Arc<RwLock<TextureManager>>, path: &Path, config: &Config) -> Result<Self> {
+        let (images, delays) = if path.is_dir() {
+            (images_from_dir(path)?, None)
+        } else {
+            let (images, delays) = images_from_animation(path)?;
+            (images, Some(delays))
+        };
+
+        let frame_size = match images.first() {
+            Some((image, _)) => [image.size[0] as u32, image.size[1] as u32],
+            None => return Err(eyre!("Stream does not contain any frames ({path:?}).")),
+        };
+
+        // Time at which each frame ends, from the config or else from the animation itself
+        let schedule: Vec<_> = match (config.stream_framerate(), delays) {
+            (Some(rate), _) => (1..=images.len())
+                .map(|i| Duration::from_secs_f64(i as f64 / rate))
+                .collect(),
+            (None, Some(delays)) if delays.iter().any(|d| !d.is_zero()) => delays
+                .iter()
+                .scan(Duration::default(), |end, delay| {
+                    *end += *delay;
+                    Some(*end)
+                })
+                .collect(),
+            _ => {
+                return Err(eyre!(
+                    "Config `stream_framerate` is required to stream an image sequence ({path:?})."
+                ))
+            }
+        };
+        let frame_rate = images.len() as f64 / schedule.last().unwrap().as_secs_f64();
+
+        let frames = images
+            .into_iter()
+            .enumerate()
+            .map(|(i, (image, size))| {
+                texture_from_image(tex_manager.clone(), &format!("{path:?}:@:{i}"), image, size)
+            })
+            .collect();
+
+        Ok(Stream {
+            path: path.to_owned(),
+            frames: Arc::new(frames),
+            frame_size,
+            frame_rate,
+            schedule: Arc::new(schedule),
+            is_eos: false,
+            clock: Default::default(),
+        })
+    }
+
+    fn cloned(
+        &self,
+        frame: Arc<Mutex<Option<(TextureId, Vec2)>>>,
+        _media_mode: StreamMode,
+        _volume: f32,
+        _io: &IoManager,
+    ) -> Result<Self> {
+        let clock = Arc::new(Mutex::new(Clock::default()));
+        {
+            let frames = self.frames.clone();
+            let schedule = self.schedule.clone();
+            let clock = Arc::downgrade(&clock);
+            thread::spawn(move || play(frames, schedule, frame, clock));
+        }
+
+        Ok(Stream {
+            path: self.path.clone(),
+            frames: self.frames.clone(),
+            frame_size: self.frame_size,
+            frame_rate: self.frame_rate,
+            schedule: self.schedule.clone(),
+            is_eos: false,
+            clock,
+        })
+    }
+
+    fn eos(&self) -> bool {
+        self.is_eos
+    }
+
+    fn size(&self) -> [u32; 2] {
+        self.frame_size
+    }
+
+    /// Average framerate, as animations can have a different delay for each frame.
+    fn framerate(&self) -> f64 {
+        self.frame_rate
+    }
+
+    fn channels(&self) -> u16 {
+        0
+    }
+
+    fn duration(&self) -> Duration {
+        self.schedule.last().copied().unwrap_or_default()
+    }
+
+    fn start(&mut self) -> Result<()> {
+        let mut clock = self.clock.lock().unwrap();
+        let now = Instant::now();
+        match (clock.origin, clock.paused_at) {
+            (None, _) => clock.origin = Some(now),
+            (Some(origin), Some(paused_at)) => {
+                clock.origin = Some(origin + (now - paused_at));
+                clock.paused_at = None;
+            }
+            (Some(_), None) => {}
+        }
+        Ok(())
+    }
+
+    fn restart(&mut self) -> Result<()> {
+        let mut clock = self.clock.lock().unwrap();
+        clock.origin = Some(Instant::now());
+        clock.paused_at = None;
+        self.is_eos = false;
+        Ok(())
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        let mut clock = self.clock.lock().unwrap();
+        if clock.origin.is_some() && clock.paused_at.is_none() {
+            clock.paused_at = Some(Instant::now());
+        }
+        Ok(())
+    }
+
+    fn pull_samples(&self) -> Result<(FrameBuffer, f64)> {
+        Ok((self.frames.clone(), self.frame_rate))
+    }
+
+    fn process_bus(&mut self, looping: bool) -> Result<bool> {
+        let finished = self.clock.lock().unwrap().elapsed() >= self.duration();
+        if !finished || self.is_eos {
+            return Ok(self.is_eos);
+        }
+
+        if looping {
+            self.restart()?;
+            Ok(false)
+        } else {
+            self.is_eos = true;
+            self.pause()?;
+            Ok(true)
+        }
+    }
+}
+
+impl Clock {
+    /// Playback time, which does not advance while paused.
+    fn elapsed(&self) -> Duration {
+        match (self.origin, self.paused_at) {
+            (None, _) => Duration::default(),
+            (Some(origin), Some(paused_at)) => paused_at.saturating_duration_since(origin),
+            (Some(origin), None) => origin.elapsed(),
+        }
+    }
+}
+
+/// Shows the frame that is due at each point of playback, until the stream is dropped.
+fn play(
+    frames: FrameBuffer,
+    schedule: Arc<Vec<Duration>>,
+    frame: Arc<Mutex<Option<(TextureId, Vec2)>>>,
+    clock: Weak<Mutex<Clock>>,
+) {
+    let sleeper = spin_sleeper();
+    let mut shown = None;
+
+    loop {
+        let (elapsed, running) = match clock.upgrade() {
+            Some(clock) => {
+                let clock = clock.lock().unwrap();
+                let running = clock.origin.is_some() && clock.paused_at.is_none();
+                (clock.elapsed(), running)
+            }
+            None => return,
+        };
+
+        // The last frame stays on screen once playback is over
+        let index = schedule
+            .partition_point(|&end| end <= elapsed)
+            .min(frames.len() - 1);
+        if shown != Some(index) {
+            *frame.lock().unwrap() = Some(frames[index]);
+            shown = Some(index);
+        }
+
+        match schedule[index].checked_sub(elapsed) {
+            Some(remaining) if running && !remaining.is_zero() => {
+                sleeper.sleep(remaining.min(POLL_INTERVAL))
+            }
+            _ => sleeper.sleep(POLL_INTERVAL),
+        }
+    }
+}
+
+/// Decodes all images of a directory, ordered by the number in their names.
+fn images_from_dir(dir: &Path) -> Result<Frames> {
+    let mut paths = vec![];
+    for entry in
+        fs::read_dir(dir).wrap_err_with(|| format!("Failed to read frame directory ({dir:?})."))?
+    {
+        let path = entry?.path();
+        let ext = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase());
+        if path.is_file() && matches!(ext.as_deref(), Some("png" | "jpg" | "jpeg")) {
+            paths.push(path);
+        }
+    }
+
+    paths.sort_by_cached_key(|path| {
+        let name = path.file_stem().unwrap().to_string_lossy().to_string();
+        (frame_number(&name), name)
+    });
+
+    let images: Frames = paths
+        .iter()
+        .map(|path| image_from_file(path))
+        .collect::<Result<_>>()?;
+
+    // Frames are drawn into the same area, so they should all be the same size
+    if let Some((first, _)) = images.first() {
+        for ((image, _), path) in images.iter().zip(paths.iter()) {
+            if image.size != first.size {
+                return Err(eyre!(
+                    "Frame has a different size than the first one in the directory \
+                    ({:?} vs {:?}): {path:?}",
+                    image.size,
+                    first.size
+                ));
+            }
+        }
+    }
+
+    Ok(images)
+}
+
+/// Decodes all frames of an animated GIF or PNG, along with the delay of each frame.
+fn images_from_animation(path: &Path) -> Result<(Frames, Vec<Duration>)> {
+    let reader = BufReader::new(
+        File::open(path).wrap_err_with(|| format!("Failed to open animation file: {path:?}"))?,
+    );
+    let ext = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase());
+    let frames = match ext.as_deref() {
+        Some("gif") => GifDecoder::new(reader)?.into_frames().collect_frames(),
+        Some("png" | "apng") => PngDecoder::new(reader)?
+            .apng()
+            .into_frames()
+            .collect_frames(),
+        _ => {
+            return Err(eyre!(
+                "Frames stream backend only supports image directories and GIF/PNG animations: \
+                {path:?}"
+            ))
+        }
+    }
+    .wrap_err_with(|| format!("Failed to decode animation file: {path:?}"))?;
+
+    Ok(frames
+        .into_iter()
+        .map(|frame| {
+            let (numer, denom) = frame.delay().numer_denom_ms();
+            let delay = Duration::from_secs_f64(numer as f64 / denom.max(1) as f64 / 1000.0);
+            let buffer = frame.into_buffer();
+            let size = [buffer.width() as _, buffer.height() as _];
+            let image = ColorImage::from_rgba_unmultiplied(size, buffer.as_raw());
+            ((image, Vec2::new(size[0] as _, size[1] as _)), delay)
+        })
+        .unzip())
+}
+
+/// The last run of digits in a frame's name, e.g. 12 for "frame_0012".
+fn frame_number(name: &str) -> Option<u64> {
+    let digits: String = name
+        .chars()
+        .rev()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.chars().rev().collect::<String>().parse().ok()
+}
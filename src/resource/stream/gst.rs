@@ -1,4 +1,4 @@
-use crate::resource::{AudioChannel, FrameBuffer, MediaStream, StreamMode};
+use crate::resource::{AudioChannel, FrameBuffer, IoManager, MediaStream, StreamMode};
 use crate::server::Config;
 use eframe::egui::mutex::RwLock;
 use eframe::egui::{ColorImage, ImageData, TextureId, TextureOptions, Vec2};
@@ -95,6 +95,7 @@ impl MediaStream for Stream {
         frame: Arc<Mutex<Option<(TextureId, Vec2)>>>,
         media_mode: StreamMode,
         volume: f32,
+        _io: &IoManager,
     ) -> Result<Self> {
         let (source, playbin) = launch(&self.path, &media_mode, volume)?;
         let bus = source.bus().unwrap();
@@ -364,12 +365,7 @@ fn pipeline(path: &Path, mode: &StreamMode) -> Result<String> {
         "\
         playbin uri=\"file://{}\" name=playbin \
         video-sink=\"videoconvert ! videoscale ! appsink name=video_sink caps=video/x-raw,format=RGBA,pixel-aspect-ratio=1/1\"",
-        path.canonicalize()
-            .wrap_err_with(|| format!(
-                "Failed to canonicalize resource path: {path:?}"
-            ))?
-            .to_str()
-            .unwrap()
+        utf8_path(path)?
     );
 
     match mode {
@@ -395,6 +391,17 @@ fn pipeline(path: &Path, mode: &StreamMode) -> Result<String> {
     Ok(pipeline)
 }
 
+/// Canonical form of `path`, which has to be valid UTF-8 to be embedded in a pipeline.
+fn utf8_path(path: &Path) -> Result<String> {
+    let canonical = path
+        .canonicalize()
+        .wrap_err_with(|| format!("Failed to canonicalize resource path: {path:?}"))?;
+    canonical
+        .to_str()
+        .map(str::to_owned)
+        .ok_or_else(|| eyre!("Resource path is not valid UTF-8: {canonical:?}"))
+}
+
 fn launch(path: &Path, mode: &StreamMode, volume: f32) -> Result<(gst::Bin, gst::Bin)> {
     let source = gst::parse_launch(&pipeline(path, mode)?)
         .wrap_err_with(|| format!("Failed to parse gstreamer command for video: {path:?}"))?
@@ -1,4 +1,4 @@
-use crate::resource::AudioChannel;
+use crate::resource::{AudioChannel, IoManager};
 use crate::server::Config;
 use eframe::egui::mutex::RwLock;
 use eframe::egui::{TextureId, Vec2};
@@ -13,6 +13,8 @@ use std::time::Duration;
 
 #[cfg(feature = "ffmpeg")]
 mod ffmpeg;
+#[cfg(feature = "frames")]
+mod frames;
 #[cfg(feature = "gstreamer")]
 mod gst;
 
@@ -26,6 +28,8 @@ pub enum Stream {
     Gst(gst::Stream),
     #[cfg(feature = "ffmpeg")]
     Ffmpeg(ffmpeg::Stream),
+    #[cfg(feature = "frames")]
+    Frames(frames::Stream),
 }
 
 pub fn stream_from_file(
@@ -54,6 +58,7 @@ where
         frame: Arc<Mutex<Option<(TextureId, Vec2)>>>,
         media_mode: StreamMode,
         volume: f32,
+        io: &IoManager,
     ) -> Result<Self>;
 
     fn eos(&self) -> bool;
@@ -91,6 +96,8 @@ pub enum StreamBackend {
     Gst,
     #[cfg(feature = "ffmpeg")]
     Ffmpeg,
+    #[cfg(feature = "frames")]
+    Frames,
 }
 
 impl Default for StreamBackend {
@@ -125,7 +132,8 @@ impl Stream {
         path: &Path,
         config: &Config,
     ) -> Result<Self> {
-        {
+        // Image sequences are streamed from a directory
+        if !path.is_dir() {
             File::open(path).wrap_err_with(|| format!("Failed to open stream file ({path:?})."))?;
         }
 
@@ -139,6 +147,10 @@ impl Stream {
             }
             #[cfg(feature = "gstreamer")]
             StreamBackend::Gst => gst::Stream::new(tex_manager, path, config).map(Stream::Gst),
+            #[cfg(feature = "frames")]
+            StreamBackend::Frames => {
+                frames::Stream::new(tex_manager, path, config).map(Stream::Frames)
+            }
         }
     }
 
@@ -151,6 +163,8 @@ impl Stream {
             Stream::Gst(stream) => stream.eos(),
             #[cfg(feature = "ffmpeg")]
             Stream::Ffmpeg(stream) => stream.eos(),
+            #[cfg(feature = "frames")]
+            Stream::Frames(stream) => stream.eos(),
         }
     }
 
@@ -163,6 +177,8 @@ impl Stream {
             Stream::Gst(stream) => stream.size(),
             #[cfg(feature = "ffmpeg")]
             Stream::Ffmpeg(stream) => stream.size(),
+            #[cfg(feature = "frames")]
+            Stream::Frames(stream) => stream.size(),
         }
     }
 
@@ -175,6 +191,8 @@ impl Stream {
             Stream::Gst(stream) => stream.framerate(),
             #[cfg(feature = "ffmpeg")]
             Stream::Ffmpeg(stream) => stream.framerate(),
+            #[cfg(feature = "frames")]
+            Stream::Frames(stream) => stream.framerate(),
         }
     }
 
@@ -187,6 +205,8 @@ impl Stream {
             Stream::Gst(stream) => stream.channels(),
             #[cfg(feature = "ffmpeg")]
             Stream::Ffmpeg(stream) => stream.channels(),
+            #[cfg(feature = "frames")]
+            Stream::Frames(stream) => stream.channels(),
         }
     }
 
@@ -199,6 +219,8 @@ impl Stream {
             Stream::Gst(stream) => stream.duration(),
             #[cfg(feature = "ffmpeg")]
             Stream::Ffmpeg(stream) => stream.duration(),
+            #[cfg(feature = "frames")]
+            Stream::Frames(stream) => stream.duration(),
         }
     }
 
@@ -211,6 +233,8 @@ impl Stream {
             Stream::Gst(stream) => stream.has_video(),
             #[cfg(feature = "ffmpeg")]
             Stream::Ffmpeg(stream) => stream.has_video(),
+            #[cfg(feature = "frames")]
+            Stream::Frames(stream) => stream.has_video(),
         }
     }
 
@@ -223,19 +247,45 @@ impl Stream {
             Stream::Gst(stream) => stream.has_audio(),
             #[cfg(feature = "ffmpeg")]
             Stream::Ffmpeg(stream) => stream.has_audio(),
+            #[cfg(feature = "frames")]
+            Stream::Frames(stream) => stream.has_audio(),
         }
     }
 
-    // /// Set the volume multiplier of the audio.
-    // /// `0.0` = 0% volume, `1.0` = 100% volume.
-    // pub fn set_volume(&mut self, volume: f64) {
-    //     self.source.set_property("volume", &volume);
-    // }
+    /// Set the volume multiplier of the audio.
+    /// `0.0` = 0% volume, `1.0` = 100% volume.
+    #[allow(unused_variables)]
+    pub fn set_volume(&mut self, volume: f64) -> Result<()> {
+        match self {
+            Stream::None => Err(eyre!("Cannot set volume of stream with backend=None.")),
+            #[cfg(feature = "gstreamer")]
+            Stream::Gst(stream) => {
+                stream.set_volume(volume);
+                Ok(())
+            }
+            #[cfg(feature = "ffmpeg")]
+            Stream::Ffmpeg(stream) => stream.set_volume(volume),
+            #[cfg(feature = "frames")]
+            Stream::Frames(_) => Ok(()),
+        }
+    }
 
-    // /// Set if the audio is muted or not, without changing the volume.
-    // pub fn set_muted(&mut self, muted: bool) {
-    //     self.source.set_property("mute", &muted);
-    // }
+    /// Set if the audio is muted or not, without changing the volume.
+    #[allow(unused_variables)]
+    pub fn set_muted(&mut self, muted: bool) -> Result<()> {
+        match self {
+            Stream::None => Err(eyre!("Cannot mute stream with backend=None.")),
+            #[cfg(feature = "gstreamer")]
+            Stream::Gst(stream) => {
+                stream.set_muted(muted);
+                Ok(())
+            }
+            #[cfg(feature = "ffmpeg")]
+            Stream::Ffmpeg(stream) => stream.set_muted(muted),
+            #[cfg(feature = "frames")]
+            Stream::Frames(_) => Ok(()),
+        }
+    }
 
     // /// Get if the stream ended or not.
     // #[inline]
@@ -270,6 +320,8 @@ impl Stream {
             Stream::Gst(stream) => stream.start(),
             #[cfg(feature = "ffmpeg")]
             Stream::Ffmpeg(stream) => stream.start(),
+            #[cfg(feature = "frames")]
+            Stream::Frames(stream) => stream.start(),
         }
     }
 
@@ -281,6 +333,8 @@ impl Stream {
             Stream::Gst(stream) => stream.restart(),
             #[cfg(feature = "ffmpeg")]
             Stream::Ffmpeg(stream) => stream.restart(),
+            #[cfg(feature = "frames")]
+            Stream::Frames(stream) => stream.restart(),
         }
     }
 
@@ -292,6 +346,8 @@ impl Stream {
             Stream::Gst(stream) => stream.pause(),
             #[cfg(feature = "ffmpeg")]
             Stream::Ffmpeg(stream) => stream.pause(),
+            #[cfg(feature = "frames")]
+            Stream::Frames(stream) => stream.pause(),
         }
     }
 
@@ -303,6 +359,8 @@ impl Stream {
             Stream::Gst(stream) => stream.process_bus(looping),
             #[cfg(feature = "ffmpeg")]
             Stream::Ffmpeg(stream) => stream.process_bus(looping),
+            #[cfg(feature = "frames")]
+            Stream::Frames(stream) => stream.process_bus(looping),
         }
     }
 
@@ -312,13 +370,16 @@ impl Stream {
         frame: Arc<Mutex<Option<(TextureId, Vec2)>>>,
         mode: StreamMode,
         volume: f32,
+        io: &IoManager,
     ) -> Result<Self> {
         match self {
             Stream::None => Err(eyre!("Cloning stream with backend=None is pointless.")),
             #[cfg(feature = "gstreamer")]
-            Stream::Gst(stream) => stream.cloned(frame, mode, volume).map(Stream::Gst),
+            Stream::Gst(stream) => stream.cloned(frame, mode, volume, io).map(Stream::Gst),
             #[cfg(feature = "ffmpeg")]
-            Stream::Ffmpeg(stream) => stream.cloned(frame, mode, volume).map(Stream::Ffmpeg),
+            Stream::Ffmpeg(stream) => stream.cloned(frame, mode, volume, io).map(Stream::Ffmpeg),
+            #[cfg(feature = "frames")]
+            Stream::Frames(stream) => stream.cloned(frame, mode, volume, io).map(Stream::Frames),
         }
     }
 
@@ -329,6 +390,8 @@ impl Stream {
             Stream::Gst(stream) => stream.pull_samples(),
             #[cfg(feature = "ffmpeg")]
             Stream::Ffmpeg(stream) => stream.pull_samples(),
+            #[cfg(feature = "frames")]
+            Stream::Frames(stream) => stream.pull_samples(),
         }
     }
 }
@@ -0,0 +1,149 @@
+use crate::comm::SignalId;
+use crate::server::SyncSignal;
+use eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_cbor::Value;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+pub const TRACE_FILE: &str = "trace.cbor";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceMode {
+    Inherit,
+    Off,
+    On,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceEntry {
+    Signal(String),
+    State(Vec<(SignalId, Value)>),
+    Start(String, String),
+    Stop(String),
+}
+
+impl Default for TraceMode {
+    #[inline(always)]
+    fn default() -> Self {
+        TraceMode::Inherit
+    }
+}
+
+impl TraceMode {
+    pub fn or(&self, other: &Self) -> Self {
+        if let Self::Inherit = self {
+            *other
+        } else {
+            *self
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_on(&self) -> bool {
+        matches!(self, TraceMode::On)
+    }
+}
+
+impl From<&SyncSignal> for TraceEntry {
+    fn from(signal: &SyncSignal) -> Self {
+        TraceEntry::Signal(match signal {
+            SyncSignal::UpdateGraph => "update_graph".to_owned(),
+            SyncSignal::KeyPress(_, keys) => format!("key_press {keys:?}"),
+            SyncSignal::KeyRelease(_, keys) => format!("key_release {keys:?}"),
+            SyncSignal::Modifiers(_, keys) => format!("modifiers {keys:?}"),
+            SyncSignal::Text(_, text) => format!("text {text:?}"),
+            SyncSignal::Emit(_, _) => "emit".to_owned(),
+            SyncSignal::Onset(_, ids) => format!("onset {ids:?}"),
+            SyncSignal::Error(e) => format!("error {e}"),
+            SyncSignal::Repaint => "repaint".to_owned(),
+            SyncSignal::Finish => "finish".to_owned(),
+            SyncSignal::Go => "go".to_owned(),
+        })
+    }
+}
+
+/// Steps through a trace recorded during a block run, keeping track of the state and the
+/// set of running actions at each step.
+pub struct TraceReplay {
+    entries: Vec<(i64, TraceEntry)>,
+    next: usize,
+    state: BTreeMap<SignalId, Value>,
+    active: BTreeMap<String, String>,
+}
+
+impl TraceReplay {
+    pub fn new(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).wrap_err_with(|| format!("Failed to open trace file ({path:?})."))?;
+
+        let mut entries = vec![];
+        for entry in serde_cbor::Deserializer::from_reader(BufReader::new(file))
+            .into_iter::<(i64, TraceEntry)>()
+        {
+            let entry =
+                entry.wrap_err_with(|| format!("Failed to parse trace file ({path:?})."))?;
+            entries.push(entry);
+        }
+
+        Ok(Self {
+            entries,
+            next: 0,
+            state: BTreeMap::new(),
+            active: BTreeMap::new(),
+        })
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    #[inline(always)]
+    pub fn position(&self) -> usize {
+        self.next
+    }
+
+    /// Applies the next entry of the trace and returns it with its timestamp (in
+    /// microseconds since the UNIX epoch).
+    pub fn step(&mut self) -> Option<&(i64, TraceEntry)> {
+        let entry = self.entries.get(self.next)?;
+        self.next += 1;
+
+        match &entry.1 {
+            TraceEntry::Signal(_) => {}
+            TraceEntry::State(changes) => {
+                for (id, value) in changes {
+                    self.state.insert(*id, value.clone());
+                }
+            }
+            TraceEntry::Start(path, type_str) => {
+                self.active.insert(path.clone(), type_str.clone());
+            }
+            TraceEntry::Stop(path) => {
+                self.active.remove(path);
+            }
+        }
+
+        Some(entry)
+    }
+
+    #[inline(always)]
+    pub fn state(&self) -> &BTreeMap<SignalId, Value> {
+        &self.state
+    }
+
+    #[inline(always)]
+    pub fn active(&self) -> &BTreeMap<String, String> {
+        &self.active
+    }
+}
@@ -0,0 +1,229 @@
+use crate::assets::VERSION;
+use crate::comm::QReader;
+use crate::resource::null_start_latency;
+use crate::server::{Block, Config, Env, ServerSignal, SyncProcessor, SyncSignal};
+use crate::util::{spin_sleeper, SystemInfo};
+use eframe::egui;
+use eyre::{eyre, Context, Result};
+use serde_cbor::Value;
+use std::fmt::{Display, Formatter};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Number of `Function` actions in each synthetic tree used to measure update throughput.
+const TREE_SIZES: [usize; 3] = [10, 100, 1000];
+/// Requested durations (in ms) used to measure the overshoot of `spin_sleeper`.
+const SLEEP_DURATIONS: [u64; 4] = [1, 5, 10, 50];
+
+/// Summary statistics of a set of measurements, in microseconds.
+pub struct Stats {
+    n: usize,
+    mean: f64,
+    p50: f64,
+    p99: f64,
+    max: f64,
+}
+
+pub struct BenchmarkReport {
+    sys_info: SystemInfo,
+    iterations: usize,
+    sections: Vec<(String, Result<Stats>)>,
+    notes: Vec<String>,
+}
+
+impl Stats {
+    fn new(samples: &[Duration]) -> Result<Self> {
+        if samples.is_empty() {
+            return Err(eyre!("No samples were collected."));
+        }
+
+        let mut us: Vec<_> = samples.iter().map(|d| d.as_secs_f64() * 1e6).collect();
+        us.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let at = |p: f64| us[((p / 100.0) * (us.len() - 1) as f64).round() as usize];
+
+        Ok(Self {
+            n: us.len(),
+            mean: us.iter().sum::<f64>() / us.len() as f64,
+            p50: at(50.0),
+            p99: at(99.0),
+            max: at(100.0),
+        })
+    }
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "n={:<6} mean={:>10.1}us  p50={:>10.1}us  p99={:>10.1}us  max={:>10.1}us",
+            self.n, self.mean, self.p50, self.p99, self.max
+        )
+    }
+}
+
+impl Display for BenchmarkReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "CogTask Server-v{VERSION} benchmark")?;
+        writeln!(f, "{:?}", self.sys_info)?;
+        writeln!(f, "Iterations: {}", self.iterations)?;
+        writeln!(f)?;
+
+        let width = self.sections.iter().map(|(s, _)| s.len()).max();
+        for (name, stats) in self.sections.iter() {
+            match stats {
+                Ok(stats) => writeln!(f, "{name:<w$}  {stats}", w = width.unwrap_or(0))?,
+                Err(e) => writeln!(f, "{name:<w$}  failed: {e}", w = width.unwrap_or(0))?,
+            }
+        }
+
+        if !self.notes.is_empty() {
+            writeln!(f)?;
+            for note in self.notes.iter() {
+                writeln!(f, "Note: {note}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Measures the overhead of the scheduler's building blocks on the current machine.
+pub fn run_benchmark(iterations: usize) -> Result<BenchmarkReport> {
+    if iterations == 0 {
+        return Err(eyre!("Benchmark requires at least one iteration."));
+    }
+
+    let mut sections = vec![(
+        "queue push-to-pop".to_owned(),
+        queue_latency(iterations).and_then(|s| Stats::new(&s)),
+    )];
+
+    for size in TREE_SIZES {
+        sections.push((
+            format!("update (tree of {size})"),
+            update_latency(size, iterations).and_then(|s| Stats::new(&s)),
+        ));
+    }
+
+    let sleeper = spin_sleeper();
+    for ms in SLEEP_DURATIONS {
+        let duration = Duration::from_millis(ms);
+        let overshoot: Vec<_> = (0..iterations)
+            .map(|_| {
+                let start = Instant::now();
+                sleeper.sleep(duration);
+                start.elapsed().saturating_sub(duration)
+            })
+            .collect();
+        sections.push((format!("wait overshoot ({ms}ms)"), Stats::new(&overshoot)));
+    }
+
+    let mut notes = vec![];
+    let audio_start = "audio start (null sink)".to_owned();
+    if cfg!(feature = "rodio") {
+        sections.push((
+            audio_start,
+            (0..iterations)
+                .map(|_| null_start_latency())
+                .collect::<Result<Vec<_>>>()
+                .and_then(|s| Stats::new(&s)),
+        ));
+    } else {
+        notes.push(format!(
+            "{audio_start} was skipped: it requires the rodio feature."
+        ));
+    }
+
+    Ok(BenchmarkReport {
+        sys_info: SystemInfo::new(),
+        iterations,
+        sections,
+        notes,
+    })
+}
+
+fn queue_latency(iterations: usize) -> Result<Vec<Duration>> {
+    let mut reader = QReader::<Instant>::new();
+    let mut writer = reader.writer();
+
+    let consumer = thread::spawn(move || {
+        (0..iterations)
+            .map_while(|_| reader.pop().map(|sent| sent.elapsed()))
+            .collect::<Vec<_>>()
+    });
+
+    let sleeper = spin_sleeper();
+    for _ in 0..iterations {
+        writer.push(Instant::now());
+        sleeper.sleep(Duration::from_micros(200));
+    }
+
+    consumer
+        .join()
+        .map_err(|_| eyre!("Queue consumer thread panicked."))
+}
+
+fn update_latency(size: usize, iterations: usize) -> Result<Vec<Duration>> {
+    let config: Config =
+        ron::from_str("(audio_backend: none)").wrap_err("Failed to create benchmark config.")?;
+
+    let function = "function((expr: \"x + 1\", vars: { \"x\": 0 }, in_mapping: { 1: \"x\" }))";
+    let block = format!(
+        "(name: \"benchmark\", tree: par(([{}])), state: {{ 1: 0 }})",
+        vec![function; size].join(", ")
+    );
+    let block: Block = ron::from_str(&block).wrap_err("Failed to create synthetic action tree.")?;
+
+    // The sync processor requests a repaint once it is done with a `Repaint` signal, which is
+    // only processed after the signals pushed before it (and those they caused)
+    let ctx = egui::Context::default();
+    let (tx, rx) = mpsc::channel();
+    let tx = Mutex::new(tx);
+    ctx.set_request_repaint_callback(move || {
+        let _ = tx.lock().unwrap().send(());
+    });
+
+    // The async reader is dropped right away, so anything logged by the tree is discarded
+    let async_writer = QReader::new().writer();
+    let mut server_reader = QReader::new();
+    let (mut sync_writer, _) = SyncProcessor::spawn(
+        &block,
+        &Env::default(),
+        &config,
+        &ctx,
+        &async_writer,
+        &server_reader.writer(),
+    )?;
+
+    let mut wait = || -> Result<bool> {
+        rx.recv()
+            .map_err(|_| eyre!("Sync processor stopped unexpectedly."))?;
+        let mut loaded = false;
+        while let Some(signal) = server_reader.try_pop() {
+            match signal {
+                ServerSignal::LoadComplete => loaded = true,
+                ServerSignal::BlockCrashed(e) => return Err(e),
+                _ => {}
+            }
+        }
+        Ok(loaded)
+    };
+
+    while !wait()? {}
+    sync_writer.push(SyncSignal::Go);
+    sync_writer.push(SyncSignal::Repaint);
+    wait()?;
+
+    let mut samples = Vec::with_capacity(iterations);
+    for i in 0..iterations {
+        let start = Instant::now();
+        let signal = vec![(1, Value::Integer(i as i128))].into();
+        sync_writer.push(SyncSignal::Emit(start, signal));
+        sync_writer.push(SyncSignal::Repaint);
+        wait()?;
+        samples.push(start.elapsed());
+    }
+
+    sync_writer.push(SyncSignal::Finish);
+    Ok(samples)
+}
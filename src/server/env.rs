@@ -1,6 +1,6 @@
 use eyre::{Context, Result};
 use std::env::current_dir;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Default, Clone)]
 pub struct Env {
@@ -21,11 +21,7 @@ impl Env {
                 .wrap_err_with(|| format!("Unable to create output directory: {output_dir:?}"))?;
         }
 
-        let resource_dir = if task_dir.join("data").exists() {
-            task_dir.join("data")
-        } else {
-            task_dir.clone()
-        };
+        let resource_dir = Self::resource_dir_of(&task_dir);
 
         Ok(Self {
             root_dir,
@@ -35,6 +31,14 @@ impl Env {
         })
     }
 
+    pub fn resource_dir_of(task_dir: &Path) -> PathBuf {
+        if task_dir.join("data").exists() {
+            task_dir.join("data")
+        } else {
+            task_dir.to_owned()
+        }
+    }
+
     #[inline(always)]
     pub fn root(&self) -> &PathBuf {
         &self.root_dir
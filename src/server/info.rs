@@ -4,29 +4,31 @@ use crate::util::Hash;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
-#[derive(Debug, Default, Deserialize, Serialize)]
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
 pub struct Info {
     subject: String,
+    #[serde(default)]
+    session: String,
     output: PathBuf,
     server: ServerInfo,
     task: TaskInfo,
     block: BlockInfo,
 }
 
-#[derive(Debug, Default, Deserialize, Serialize)]
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
 pub struct TaskInfo {
     name: String,
     version: String,
     hash: String,
 }
 
-#[derive(Debug, Default, Deserialize, Serialize)]
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
 pub struct BlockInfo {
     name: String,
     hash: String,
 }
 
-#[derive(Debug, Default, Deserialize, Serialize)]
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
 pub struct ServerInfo {
     version: String,
     hash: String,
@@ -36,6 +38,7 @@ impl Info {
     pub fn new(server: &Server, task: &Task, block: &Block) -> Self {
         Self {
             subject: server.subject().to_owned(),
+            session: server.session().to_owned(),
             output: server.env().output().join(server.subject()),
             server: ServerInfo {
                 version: VERSION.to_owned(),
@@ -58,6 +61,16 @@ impl Info {
         &self.subject
     }
 
+    #[inline(always)]
+    pub fn session(&self) -> &String {
+        &self.session
+    }
+
+    #[inline(always)]
+    pub fn task(&self) -> &String {
+        &self.task.name
+    }
+
     #[inline(always)]
     pub fn block(&self) -> &String {
         &self.block.name
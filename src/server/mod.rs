@@ -1,9 +1,11 @@
+pub mod benchmark;
 pub mod env;
 pub mod info;
 pub mod page;
 pub mod scheduler;
 pub mod task;
 
+pub use benchmark::*;
 pub use env::Env;
 pub use info::*;
 pub use page::*;
@@ -37,6 +39,7 @@ pub struct Server {
     env: Env,
     task: Task,
     subject: String,
+    session: String,
     scale_factor: u32,
     hold_on_rescale: bool,
     scheduler: Option<Scheduler>,
@@ -68,6 +71,7 @@ impl Server {
             env,
             task,
             subject: "".to_owned(),
+            session: Local::now().format("%F_%H-%M-%S").to_string(),
             scale_factor: 100,
             hold_on_rescale: false,
             scheduler: None,
@@ -140,6 +144,11 @@ impl Server {
         &self.subject
     }
 
+    #[inline(always)]
+    pub fn session(&self) -> &String {
+        &self.session
+    }
+
     #[inline(always)]
     pub fn active_block(&self) -> Option<&Block> {
         self.active_block.map(|i| self.task.block(i))
@@ -251,6 +260,10 @@ pub enum ServerSignal {
 
 impl App for Server {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if let (Page::Activity, Some(scheduler)) = (self.page, self.scheduler.as_mut()) {
+            scheduler.post_present();
+        }
+
         while let Some(signal) = self.sync_reader.try_pop() {
             self.process(ctx, signal);
         }
@@ -276,4 +289,10 @@ impl App for Server {
             ctx.request_repaint_after(Duration::from_millis(250));
         }
     }
+
+    fn post_rendering(&mut self, _window_size_px: [u32; 2], _frame: &eframe::Frame) {
+        if let (Page::Activity, Some(scheduler)) = (self.page, self.scheduler.as_mut()) {
+            scheduler.post_rendering();
+        }
+    }
 }
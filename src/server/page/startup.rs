@@ -2,6 +2,7 @@ use crate::assets::Icon;
 use crate::gui::{
     header_body_controls, style_ui, text::body, text::button1, text::inactive, text::tooltip, Style,
 };
+use crate::resource::{run_dirs, SignedManifest, MANIFEST_FILE};
 use crate::server::{Page, Progress, Server};
 use chrono::{NaiveDate, NaiveTime};
 use eframe::egui;
@@ -162,34 +163,49 @@ impl Server {
     }
 
     fn update_history(&mut self, i: usize) -> Result<()> {
-        let name = self.blocks[i].0.to_snake_case();
-        let progress = &mut self.blocks[i].1;
+        let label = &self.blocks[i].0;
+        let name = label.to_snake_case();
 
         let mut last = None;
         let dir = self.env.output().join(&self.subject);
-        if let Ok(sessions) = dir.read_dir() {
-            for session in sessions {
-                let date = session.unwrap().file_name().to_str().unwrap().to_owned();
-                let dir = dir.join(&date);
-                if let Ok(date) = NaiveDate::parse_from_str(&date, "%Y-%m-%d") {
-                    if let Ok(runs) = dir.join(&name).read_dir() {
-                        for run in runs {
-                            let time = run.unwrap().file_name().to_str().unwrap().to_owned();
-                            if let Ok(time) = NaiveTime::parse_from_str(&time, "%H-%M-%S") {
-                                let datetime = date.and_time(time);
-
-                                match last {
-                                    None => last = Some(datetime),
-                                    Some(t) if datetime > t => last = Some(datetime),
-                                    _ => {}
-                                }
-                            }
-                        }
-                    }
+        for run in run_dirs(&dir)? {
+            let datetime = if let Ok(signed) = SignedManifest::read(&run.join(MANIFEST_FILE)) {
+                if signed.manifest().info().block() != label {
+                    continue;
+                }
+                match signed.manifest().time() {
+                    Ok(time) => time.naive_local(),
+                    Err(_) => continue,
                 }
+            } else {
+                // Runs without a manifest can only be recognized in the default layout
+                let parts: Vec<_> = run
+                    .strip_prefix(&dir)
+                    .unwrap()
+                    .iter()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .collect();
+                if parts.len() != 3 || parts[1] != name {
+                    continue;
+                }
+
+                match (
+                    NaiveDate::parse_from_str(&parts[0], "%Y-%m-%d"),
+                    NaiveTime::parse_from_str(&parts[2], "%H-%M-%S"),
+                ) {
+                    (Ok(date), Ok(time)) => date.and_time(time),
+                    _ => continue,
+                }
+            };
+
+            match last {
+                None => last = Some(datetime),
+                Some(t) if datetime > t => last = Some(datetime),
+                _ => {}
             }
         }
 
+        let progress = &mut self.blocks[i].1;
         if let Some(t) = last {
             *progress = Progress::LastRun(t);
         }
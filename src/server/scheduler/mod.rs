@@ -1,13 +1,18 @@
+pub mod onset;
 pub mod p_async;
 pub mod p_sync;
+pub mod timing;
 
+pub use onset::*;
 pub use p_async::*;
 pub use p_sync::*;
+pub use timing::*;
 
 use crate::action::StatefulAction;
 use crate::comm::QWriter;
-use crate::resource::{LoggerSignal, TAG_ACTION, TAG_CONFIG, TAG_INFO};
+use crate::resource::{Key, LoggerSignal, TAG_ACTION, TAG_CONFIG, TAG_INFO};
 use crate::server::{Config, Info, Server, ServerSignal};
+use chrono::Local;
 use eframe::egui;
 use eframe::egui::{CentralPanel, CursorIcon, Event, Frame};
 use eyre::Result;
@@ -24,6 +29,12 @@ pub struct Scheduler {
     info: Info,
     last_esc: Option<SystemTime>,
     config: Config,
+    frame: u64,
+    pending_onsets: Vec<Onset>,
+    presenting_onsets: Option<(u64, Vec<Onset>)>,
+    timing: FrameTiming,
+    continuous: bool,
+    modifiers: BTreeSet<Key>,
     ctx: egui::Context,
     sync_writer: QWriter<SyncSignal>,
     async_writer: QWriter<AsyncSignal>,
@@ -36,7 +47,7 @@ impl Scheduler {
         let task = server.task();
         let block = server.active_block().unwrap();
         let info = Info::new(server, task, block);
-        let config = block.config(server.config());
+        let config = block.config(server.config())?;
 
         let server_writer = server.callback_channel();
         let mut async_writer = AsyncProcessor::spawn(&info, &config, &server_writer)?;
@@ -61,11 +72,19 @@ impl Scheduler {
             ],
         ));
 
+        let timing = FrameTiming::new(config.refresh_rate());
+
         Ok(Self {
             atomic,
             info,
             last_esc: None,
             config,
+            frame: 0,
+            pending_onsets: vec![],
+            presenting_onsets: None,
+            timing,
+            continuous: false,
+            modifiers: BTreeSet::new(),
             ctx: ctx.clone(),
             sync_writer,
             async_writer,
@@ -97,6 +116,10 @@ impl Scheduler {
     }
 
     pub fn show(&mut self, ui: &mut egui::Ui) -> Result<()> {
+        self.frame += 1;
+        // egui only trusts the time since the last frame if a repaint was requested during it
+        self.continuous = ui.input(|i| i.stable_dt == i.unstable_dt);
+
         if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
             let time = SystemTime::now();
             if let Some(t) = self.last_esc.take() {
@@ -108,27 +131,45 @@ impl Scheduler {
             self.last_esc = Some(time);
         }
 
-        let keys_pressed: BTreeSet<_> = ui.input(|i| {
-            i.events
-                .iter()
-                .filter_map(|e| {
-                    if let Event::Key {
+        // Events are forwarded in the order they occurred. egui does not timestamp them, so
+        // all the events of a frame share the time at which the frame started to be drawn.
+        // Modifiers are reported as a state rather than events, and are kept out of the key
+        // stream so that only actions that opt into them see them as keys.
+        let now = Instant::now();
+        let signals = ui.input(|i| {
+            let mut signals = vec![];
+            let mut track = |signals: &mut Vec<SyncSignal>, modifiers: &egui::Modifiers| {
+                let modifiers = Key::modifiers(modifiers);
+                if modifiers != self.modifiers {
+                    self.modifiers = modifiers;
+                    signals.push(SyncSignal::Modifiers(now, self.modifiers.clone()));
+                }
+            };
+
+            for e in i.events.iter() {
+                match e {
+                    Event::Key {
                         key: k,
-                        pressed: true,
-                        repeat: false,
-                        ..
-                    } = e
-                    {
-                        Some(k.into())
-                    } else {
-                        None
+                        pressed,
+                        repeat,
+                        modifiers,
+                    } => {
+                        track(&mut signals, modifiers);
+                        if !pressed {
+                            signals.push(SyncSignal::KeyRelease(now, BTreeSet::from([k.into()])));
+                        } else if !repeat {
+                            signals.push(SyncSignal::KeyPress(now, BTreeSet::from([k.into()])));
+                        }
                     }
-                })
-                .collect()
+                    Event::Text(t) => signals.push(SyncSignal::Text(now, t.clone())),
+                    _ => {}
+                }
+            }
+            track(&mut signals, &i.modifiers);
+            signals
         });
-        if !keys_pressed.is_empty() {
-            self.sync_writer
-                .push(SyncSignal::KeyPress(Instant::now(), keys_pressed))
+        for signal in signals {
+            self.sync_writer.push(signal);
         }
 
         ui.output_mut(|o| o.cursor_icon = CursorIcon::None);
@@ -145,6 +186,7 @@ impl Scheduler {
                 })
                 .inner
         };
+        self.pending_onsets.extend(take_onsets(ui.ctx()));
 
         if let Err(e) = &result {
             self.async_writer.push(LoggerSignal::Append(
@@ -156,6 +198,51 @@ impl Scheduler {
         Ok(())
     }
 
+    /// Called after the frame has been painted, but before its buffers are swapped. The frame
+    /// is added to the timing audit, and onsets registered while drawing it are held back until
+    /// the frame has been presented (see [`Self::post_present`]).
+    pub fn post_rendering(&mut self) {
+        self.timing.frame(Instant::now(), self.continuous);
+
+        if !self.pending_onsets.is_empty() {
+            let onsets = std::mem::take(&mut self.pending_onsets);
+            self.presenting_onsets = Some((self.frame, onsets));
+            self.ctx.request_repaint();
+        }
+    }
+
+    /// Called at the start of the frame following one with onsets, i.e., once the buffers of
+    /// the latter have been swapped. Its onsets are timestamped and logged under `stimulus`
+    /// along with the frame number. Their output signals receive that time, in seconds since
+    /// the start of the block.
+    pub fn post_present(&mut self) {
+        if let Some((frame, onsets)) = self.presenting_onsets.take() {
+            let time = Instant::now();
+            let now = Local::now();
+
+            let frame = Value::Integer(frame as i128);
+            self.async_writer.push(AsyncSignal::Logger(
+                now,
+                LoggerSignal::Extend(
+                    "stimulus".to_owned(),
+                    onsets
+                        .iter()
+                        .map(|o| (o.name().to_owned(), frame.clone()))
+                        .collect(),
+                ),
+            ));
+
+            let ids: Vec<_> = onsets
+                .iter()
+                .map(|o| o.out_signal())
+                .filter(|&id| id > 0)
+                .collect();
+            if !ids.is_empty() {
+                self.sync_writer.push(SyncSignal::Onset(time, ids));
+            }
+        }
+    }
+
     pub fn sync_writer(&mut self) -> &mut QWriter<SyncSignal> {
         &mut self.sync_writer
     }
@@ -167,6 +254,13 @@ impl Scheduler {
 
 impl Drop for Scheduler {
     fn drop(&mut self) {
+        self.post_present();
+
+        self.async_writer.push(LoggerSignal::Write(
+            "frame_timing".to_owned(),
+            self.timing.summary(),
+        ));
+
         self.async_writer.push(LoggerSignal::Append(
             "main".to_owned(),
             ("finish".to_owned(), Value::Text("ok".to_owned())),
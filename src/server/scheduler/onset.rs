@@ -0,0 +1,38 @@
+use crate::comm::SignalId;
+use eframe::egui;
+
+#[derive(Debug, Clone)]
+pub struct Onset {
+    name: String,
+    out_signal: SignalId,
+}
+
+impl Onset {
+    #[inline(always)]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[inline(always)]
+    pub fn out_signal(&self) -> SignalId {
+        self.out_signal
+    }
+}
+
+#[inline(always)]
+fn onset_id() -> egui::Id {
+    egui::Id::new("cog-task::onsets")
+}
+
+/// Marks a visual action as newly visible in the frame that is currently being drawn. The
+/// scheduler timestamps the onset once that frame has been rendered.
+pub fn register_onset(ui: &egui::Ui, name: String, out_signal: SignalId) {
+    ui.ctx().data_mut(|d| {
+        d.get_temp_mut_or_default::<Vec<Onset>>(onset_id())
+            .push(Onset { name, out_signal })
+    });
+}
+
+pub(crate) fn take_onsets(ctx: &egui::Context) -> Vec<Onset> {
+    ctx.data_mut(|d| std::mem::take(d.get_temp_mut_or_default::<Vec<Onset>>(onset_id())))
+}
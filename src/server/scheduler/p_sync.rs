@@ -1,21 +1,38 @@
 use crate::action::nil::StatefulNil;
+use crate::action::trace::{is_tracing, set_tracing};
 use crate::action::{Action, ActionSignal, StatefulAction};
-use crate::comm::{QReader, QWriter, Signal, MAX_QUEUE_SIZE};
-use crate::resource::{IoManager, Key, LoggerSignal, ResourceManager};
+use crate::comm::{QReader, QWriter, Signal, SignalId, MAX_QUEUE_SIZE};
+use crate::resource::{IoManager, Key, LoggerSignal, ResourceManager, TraceEntry};
 use crate::server::{AsyncSignal, Atomic, Block, Config, Env, ServerSignal};
 use eframe::egui;
 use eyre::{eyre, Context, Error, Result};
 use serde_cbor::{from_slice, Value};
+use std::cell::Cell;
 use std::collections::{BTreeSet, VecDeque};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 
+thread_local! {
+    static BLOCK_START: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+/// Time at which the action tree of the running block was started. Only set in the thread
+/// that processes sync signals.
+#[inline(always)]
+pub fn block_start() -> Option<Instant> {
+    BLOCK_START.with(|t| t.get())
+}
+
 #[derive(Debug)]
 pub enum SyncSignal {
     UpdateGraph,
     KeyPress(Instant, BTreeSet<Key>),
+    KeyRelease(Instant, BTreeSet<Key>),
+    Modifiers(Instant, BTreeSet<Key>),
+    Text(Instant, String),
     Emit(Instant, Signal),
+    Onset(Instant, Vec<SignalId>),
     Error(Error),
     Repaint,
     Finish,
@@ -35,8 +52,13 @@ impl PartialEq for SyncSignal {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (SyncSignal::UpdateGraph, SyncSignal::UpdateGraph) => true,
-            (SyncSignal::KeyPress(t1, _), SyncSignal::KeyPress(t2, _)) => t1 == t2,
+            // Input events of a frame share its time, but are distinct events
+            (SyncSignal::KeyPress(_, _), SyncSignal::KeyPress(_, _)) => false,
+            (SyncSignal::KeyRelease(_, _), SyncSignal::KeyRelease(_, _)) => false,
+            (SyncSignal::Modifiers(_, _), SyncSignal::Modifiers(_, _)) => false,
+            (SyncSignal::Text(_, _), SyncSignal::Text(_, _)) => false,
             (SyncSignal::Emit(_, _), SyncSignal::Emit(_, _)) => false,
+            (SyncSignal::Onset(_, _), SyncSignal::Onset(_, _)) => false,
             (SyncSignal::Repaint, SyncSignal::Repaint) => true,
             (SyncSignal::Finish, SyncSignal::Finish) => true,
             _ => false,
@@ -98,6 +120,11 @@ impl SyncProcessor {
                 }
             };
 
+            #[cfg(feature = "rodio")]
+            if let Some(capture) = io_manager.capture() {
+                proc.async_writer.push(LoggerSignal::Capture(capture));
+            }
+
             let mut res_manager = match ResourceManager::new(&config) {
                 Ok(r) => r,
                 Err(e) => {
@@ -119,6 +146,8 @@ impl SyncProcessor {
                 return;
             }
 
+            let trace = config.trace().is_on();
+            set_tracing(trace);
             let tree = match from_slice::<Box<dyn Action>>(&tree) {
                 Ok(tree) => tree,
                 Err(e) => {
@@ -175,6 +204,11 @@ impl SyncProcessor {
                     #[cfg(debug_assertions)]
                     println!("{signal:?}");
 
+                    if trace {
+                        proc.async_writer
+                            .push(LoggerSignal::Trace(TraceEntry::from(&signal)));
+                    }
+
                     let news = match signal {
                         SyncSignal::UpdateGraph => {
                             let (tree, state) = &mut *proc.atomic.lock().unwrap();
@@ -196,6 +230,36 @@ impl SyncProcessor {
                             )
                             .wrap_err("Failed to process key press.")
                         }
+                        SyncSignal::KeyRelease(time, keys) => {
+                            let (tree, state) = &mut *proc.atomic.lock().unwrap();
+                            tree.update(
+                                &ActionSignal::KeyRelease(time, keys),
+                                &mut proc.sync_writer,
+                                &mut proc.async_writer,
+                                state,
+                            )
+                            .wrap_err("Failed to process key release.")
+                        }
+                        SyncSignal::Modifiers(time, keys) => {
+                            let (tree, state) = &mut *proc.atomic.lock().unwrap();
+                            tree.update(
+                                &ActionSignal::Modifiers(time, keys),
+                                &mut proc.sync_writer,
+                                &mut proc.async_writer,
+                                state,
+                            )
+                            .wrap_err("Failed to process modifier keys.")
+                        }
+                        SyncSignal::Text(time, text) => {
+                            let (tree, state) = &mut *proc.atomic.lock().unwrap();
+                            tree.update(
+                                &ActionSignal::Text(time, text),
+                                &mut proc.sync_writer,
+                                &mut proc.async_writer,
+                                state,
+                            )
+                            .wrap_err("Failed to process text input.")
+                        }
                         SyncSignal::Emit(time, signal) => {
                             let (tree, state) = &mut *proc.atomic.lock().unwrap();
 
@@ -207,6 +271,12 @@ impl SyncProcessor {
                                 }
                             }
 
+                            if trace && !changed.is_empty() {
+                                let values = changed.iter().map(|k| (*k, state[k].clone()));
+                                proc.async_writer
+                                    .push(LoggerSignal::Trace(TraceEntry::State(values.collect())));
+                            }
+
                             tree.update(
                                 &ActionSignal::StateChanged(time, changed),
                                 &mut proc.sync_writer,
@@ -215,6 +285,19 @@ impl SyncProcessor {
                             )
                             .wrap_err("Failed to emit signal.")
                         }
+                        SyncSignal::Onset(time, ids) => {
+                            // Onsets are emitted in seconds since the start of the block
+                            let onset = time
+                                .saturating_duration_since(block_start().unwrap_or(time))
+                                .as_secs_f64();
+                            let signal = ids
+                                .into_iter()
+                                .map(|id| (id, Value::Float(onset)))
+                                .collect::<Vec<_>>()
+                                .into();
+                            signals.push_front(SyncSignal::Emit(time, signal));
+                            Ok(Signal::none())
+                        }
                         SyncSignal::Error(e) => Err(e),
                         SyncSignal::Repaint => {
                             proc.ctx.request_repaint();
@@ -293,7 +376,14 @@ impl SyncProcessor {
             ("start".to_owned(), Value::Text("ok".to_owned())),
         ));
 
+        if is_tracing() {
+            let values = state.iter().map(|(k, v)| (*k, v.clone()));
+            self.async_writer
+                .push(LoggerSignal::Trace(TraceEntry::State(values.collect())));
+        }
+
         *tree = root;
+        BLOCK_START.with(|t| t.set(Some(Instant::now())));
         let news = tree.start(&mut self.sync_writer, &mut self.async_writer, state)?;
         if !news.is_empty() {
             self.sync_writer.push(SyncSignal::from(news));
@@ -0,0 +1,110 @@
+use serde_cbor::Value;
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+/// A frame is late if it arrives more than this many refresh periods after the previous one.
+const LATE_THRESHOLD: f64 = 1.5;
+/// Histogram bins are this fraction of a refresh period wide.
+const BINS_PER_PERIOD: usize = 4;
+/// Intervals longer than this many refresh periods fall in the last histogram bin.
+const MAX_PERIODS: usize = 4;
+
+/// Collects inter-frame intervals during a block run and summarizes them relative to a
+/// target refresh rate.
+pub struct FrameTiming {
+    period: f64,
+    frames: u64,
+    last: Option<Instant>,
+    intervals: Vec<f64>,
+}
+
+impl FrameTiming {
+    pub fn new(refresh_rate: f32) -> Self {
+        Self {
+            period: 1000.0 / refresh_rate as f64,
+            frames: 0,
+            last: None,
+            intervals: vec![],
+        }
+    }
+
+    /// Records a rendered frame. The interval since the previous frame is only measured if
+    /// a repaint was requested right after it; otherwise the window was idle in between.
+    pub fn frame(&mut self, time: Instant, continuous: bool) {
+        self.frames += 1;
+        if let (Some(last), true) = (self.last, continuous) {
+            self.intervals
+                .push(time.duration_since(last).as_secs_f64() * 1000.0);
+        }
+        self.last = Some(time);
+    }
+
+    pub fn summary(&self) -> Value {
+        let mut sorted = self.intervals.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let percentile = |p: f64| -> Value {
+            if sorted.is_empty() {
+                Value::Null
+            } else {
+                let i = ((p / 100.0) * (sorted.len() - 1) as f64).round() as usize;
+                Value::Float(sorted[i])
+            }
+        };
+
+        let mean = if sorted.is_empty() {
+            Value::Null
+        } else {
+            Value::Float(sorted.iter().sum::<f64>() / sorted.len() as f64)
+        };
+
+        let late = sorted
+            .iter()
+            .filter(|&&t| t > LATE_THRESHOLD * self.period)
+            .count();
+        let dropped: u64 = sorted
+            .iter()
+            .map(|t| ((t / self.period).round() as u64).saturating_sub(1))
+            .sum();
+
+        let width = self.period / BINS_PER_PERIOD as f64;
+        let n_bins = BINS_PER_PERIOD * MAX_PERIODS + 1;
+        let mut counts = vec![0_u64; n_bins];
+        for t in sorted.iter() {
+            counts[((t / width) as usize).min(n_bins - 1)] += 1;
+        }
+        let histogram = counts
+            .into_iter()
+            .enumerate()
+            .map(|(i, n)| {
+                Value::Array(vec![
+                    Value::Float(i as f64 * width),
+                    Value::Integer(n as i128),
+                ])
+            })
+            .collect();
+
+        let text = |s: &str| Value::Text(s.to_owned());
+        Value::Map(BTreeMap::from([
+            (text("refresh_rate"), Value::Float(1000.0 / self.period)),
+            (text("period_ms"), Value::Float(self.period)),
+            (text("frames"), Value::Integer(self.frames as i128)),
+            (text("intervals"), Value::Integer(sorted.len() as i128)),
+            (text("mean_ms"), mean),
+            (
+                text("percentiles_ms"),
+                Value::Map(BTreeMap::from([
+                    (text("min"), percentile(0.0)),
+                    (text("p50"), percentile(50.0)),
+                    (text("p90"), percentile(90.0)),
+                    (text("p95"), percentile(95.0)),
+                    (text("p99"), percentile(99.0)),
+                    (text("max"), percentile(100.0)),
+                ])),
+            ),
+            (text("late"), Value::Integer(late as i128)),
+            (text("dropped"), Value::Integer(dropped as i128)),
+            (text("histogram_ms"), Value::Array(histogram)),
+        ]))
+    }
+}
@@ -88,7 +88,7 @@ impl Block {
     }
 
     #[inline]
-    pub fn config(&self, base_config: &Config) -> Config {
+    pub fn config(&self, base_config: &Config) -> Result<Config> {
         self.config.fill_blanks(base_config)
     }
 }
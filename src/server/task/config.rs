@@ -1,5 +1,7 @@
 use crate::resource::{
-    AudioBackend, Color, Interpreter, LogFormat, StreamBackend, TimePrecision, Volume,
+    AudioBackend, AudioFormat, AudioOutput, Color, Fade, Interpreter, LockPolicy, LogCollision,
+    LogFlush, LogFormat, OptionalFloat, OptionalString, OptionalUInt, StreamBackend, TimePrecision,
+    TraceMode, Volume,
 };
 use eyre::{eyre, Result};
 use serde::{Deserialize, Serialize};
@@ -14,23 +16,50 @@ pub struct Config {
     blocks_per_row: i32,
     #[serde(default = "defaults::volume")]
     volume: Volume,
+    #[serde(default = "defaults::fade")]
+    fade_in: Fade,
+    #[serde(default = "defaults::fade")]
+    fade_out: Fade,
     #[serde(default = "defaults::log_format")]
     log_format: LogFormat,
+    #[serde(default = "defaults::log_flush")]
+    log_flush: LogFlush,
+    #[serde(default = "defaults::log_collision")]
+    log_collision: LogCollision,
+    #[serde(default = "defaults::output_path")]
+    output_path: String,
     #[serde(default = "defaults::time_precision")]
     time_precision: TimePrecision,
     #[serde(default = "defaults::interpreter")]
     interpreter: Interpreter,
     #[serde(default = "defaults::audio_backend")]
     audio_backend: AudioBackend,
+    #[serde(default)]
+    audio_device: AudioOutput,
+    #[serde(default)]
+    audio_channels: OptionalUInt,
+    #[serde(default)]
+    audio_sample_rate: OptionalUInt,
+    #[serde(default = "defaults::audio_format")]
+    audio_format: AudioFormat,
     #[serde(default = "defaults::stream_backend")]
     stream_backend: StreamBackend,
+    #[serde(default)]
+    stream_framerate: OptionalFloat,
     #[serde(default = "defaults::background")]
     background: Color,
+    #[serde(default = "defaults::resource_lock")]
+    resource_lock: LockPolicy,
+    #[serde(default = "defaults::trace")]
+    trace: TraceMode,
+    #[serde(default = "defaults::refresh_rate")]
+    refresh_rate: f32,
 }
 
 mod defaults {
     use crate::resource::{
-        AudioBackend, Color, Interpreter, LogFormat, StreamBackend, TimePrecision, Volume,
+        AudioBackend, AudioFormat, Color, Fade, Interpreter, LockPolicy, LogCollision, LogFlush,
+        LogFormat, StreamBackend, TimePrecision, TraceMode, Volume,
     };
     use cfg_if::cfg_if;
 
@@ -49,11 +78,31 @@ mod defaults {
         Volume::Value(1.0)
     }
 
+    #[inline(always)]
+    pub fn fade() -> Fade {
+        Fade::Value(0.0)
+    }
+
     #[inline(always)]
     pub fn log_format() -> LogFormat {
         LogFormat::RON
     }
 
+    #[inline(always)]
+    pub fn log_flush() -> LogFlush {
+        LogFlush::Interval(5.0)
+    }
+
+    #[inline(always)]
+    pub fn log_collision() -> LogCollision {
+        LogCollision::Abort
+    }
+
+    #[inline(always)]
+    pub fn output_path() -> String {
+        "{date}/{block}/{time}".to_owned()
+    }
+
     #[inline(always)]
     pub fn time_precision() -> TimePrecision {
         TimePrecision::RespectBoundaries
@@ -75,6 +124,11 @@ mod defaults {
         }
     }
 
+    #[inline(always)]
+    pub fn audio_format() -> AudioFormat {
+        AudioFormat::Native
+    }
+
     #[inline(always)]
     pub fn stream_backend() -> StreamBackend {
         cfg_if! {
@@ -82,6 +136,8 @@ mod defaults {
                 StreamBackend::Gst
             } else if #[cfg(feature = "ffmpeg")] {
                 StreamBackend::Ffmpeg
+            } else if #[cfg(feature = "frames")] {
+                StreamBackend::Frames
             } else {
                 StreamBackend::None
             }
@@ -92,17 +148,70 @@ mod defaults {
     pub fn background() -> Color {
         Color::Transparent
     }
+
+    #[inline(always)]
+    pub fn resource_lock() -> LockPolicy {
+        LockPolicy::Enforce
+    }
+
+    #[inline(always)]
+    pub fn trace() -> TraceMode {
+        TraceMode::Off
+    }
+
+    #[inline(always)]
+    pub fn refresh_rate() -> f32 {
+        60.0
+    }
 }
 
 impl Config {
     pub fn init(&mut self) -> Result<()> {
         self.volume = self.volume.or(&defaults::volume());
+        self.fade_in = self.fade_in.or(&defaults::fade());
+        self.fade_out = self.fade_out.or(&defaults::fade());
+        self.fade_in.verify()?;
+        self.fade_out.verify()?;
         self.time_precision = self.time_precision.or(&defaults::time_precision());
         self.log_format = self.log_format.or(&defaults::log_format());
+        self.log_flush = self.log_flush.or(&defaults::log_flush());
+        self.log_flush.verify()?;
+        self.log_collision = self.log_collision.or(&defaults::log_collision());
+        if self.output_path.is_empty() {
+            self.output_path = defaults::output_path();
+        }
         self.interpreter = self.interpreter.or(&defaults::interpreter());
         self.audio_backend = self.audio_backend.or(&defaults::audio_backend());
+        if let Some(&channels) = self.audio_channels.as_ref() {
+            if channels == 0 || channels > u16::MAX as u64 {
+                return Err(eyre!(
+                    "Config `audio_channels` should be between 1 and {} ({channels}).",
+                    u16::MAX
+                ));
+            }
+        }
+        if let Some(&rate) = self.audio_sample_rate.as_ref() {
+            if rate == 0 || rate > u32::MAX as u64 {
+                return Err(eyre!(
+                    "Config `audio_sample_rate` should be a positive number ({rate})."
+                ));
+            }
+        }
+        self.audio_format = self.audio_format.or(&defaults::audio_format());
         self.stream_backend = self.stream_backend.or(&defaults::stream_backend());
+        if let Some(&rate) = self.stream_framerate.as_ref() {
+            if !rate.is_finite() || rate <= 0.0 {
+                return Err(eyre!(
+                    "Config `stream_framerate` should be a positive number ({rate})."
+                ));
+            }
+        }
         self.background = self.background.or(&defaults::background());
+        self.resource_lock = self.resource_lock.or(&defaults::resource_lock());
+        self.trace = self.trace.or(&defaults::trace());
+        if !self.refresh_rate.is_finite() || self.refresh_rate <= 0.0 {
+            return Err(eyre!("Config `refresh_rate` should be a positive number."));
+        }
         Ok(())
     }
 
@@ -111,6 +220,16 @@ impl Config {
         self.volume
     }
 
+    #[inline(always)]
+    pub fn fade_in(&self) -> Fade {
+        self.fade_in
+    }
+
+    #[inline(always)]
+    pub fn fade_out(&self) -> Fade {
+        self.fade_out
+    }
+
     pub fn verify_checksum(&self, task: String) -> Result<()> {
         if let Some(checksum) = self.verify_sha2.as_ref() {
             if checksum != &task {
@@ -134,6 +253,21 @@ impl Config {
         self.log_format
     }
 
+    #[inline(always)]
+    pub fn log_flush(&self) -> LogFlush {
+        self.log_flush
+    }
+
+    #[inline(always)]
+    pub fn log_collision(&self) -> LogCollision {
+        self.log_collision
+    }
+
+    #[inline(always)]
+    pub fn output_path(&self) -> &str {
+        &self.output_path
+    }
+
     #[inline(always)]
     pub fn time_precision(&self) -> TimePrecision {
         self.time_precision
@@ -149,46 +283,158 @@ impl Config {
         self.audio_backend
     }
 
+    #[inline(always)]
+    pub fn audio_device(&self) -> AudioOutput {
+        self.audio_device.clone()
+    }
+
+    #[inline(always)]
+    pub fn audio_channels(&self) -> Option<u16> {
+        // Range is checked by `init` and `fill_blanks`
+        self.audio_channels.as_ref().map(|&c| c as u16)
+    }
+
+    #[inline(always)]
+    pub fn audio_sample_rate(&self) -> Option<u32> {
+        self.audio_sample_rate.as_ref().map(|&r| r as u32)
+    }
+
+    #[inline(always)]
+    pub fn audio_format(&self) -> AudioFormat {
+        self.audio_format
+    }
+
     #[inline(always)]
     pub fn stream_backend(&self) -> StreamBackend {
         self.stream_backend
     }
 
+    #[inline(always)]
+    pub fn stream_framerate(&self) -> Option<f64> {
+        self.stream_framerate.as_ref().copied()
+    }
+
     #[inline(always)]
     pub fn background(&self) -> Color {
         self.background
     }
+
+    #[inline(always)]
+    pub fn resource_lock(&self) -> LockPolicy {
+        self.resource_lock
+    }
+
+    #[inline(always)]
+    pub fn trace(&self) -> TraceMode {
+        self.trace
+    }
+
+    #[inline(always)]
+    pub fn refresh_rate(&self) -> f32 {
+        self.refresh_rate
+    }
 }
 
-#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize)]
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct OptionalConfig {
     #[serde(default)]
     volume: Volume,
     #[serde(default)]
+    fade_in: Fade,
+    #[serde(default)]
+    fade_out: Fade,
+    #[serde(default)]
     log_format: LogFormat,
     #[serde(default)]
+    log_flush: LogFlush,
+    #[serde(default)]
+    log_collision: LogCollision,
+    #[serde(default)]
+    output_path: OptionalString,
+    #[serde(default)]
     time_precision: TimePrecision,
     #[serde(default)]
     interpreter: Interpreter,
     #[serde(default)]
     audio_backend: AudioBackend,
     #[serde(default)]
+    audio_device: AudioOutput,
+    #[serde(default)]
+    audio_channels: OptionalUInt,
+    #[serde(default)]
+    audio_sample_rate: OptionalUInt,
+    #[serde(default)]
+    audio_format: AudioFormat,
+    #[serde(default)]
     stream_backend: StreamBackend,
     #[serde(default)]
+    stream_framerate: OptionalFloat,
+    #[serde(default)]
     background: Color,
+    #[serde(default)]
+    resource_lock: LockPolicy,
+    #[serde(default)]
+    trace: TraceMode,
+    #[serde(default)]
+    refresh_rate: OptionalFloat,
 }
 
 impl OptionalConfig {
-    pub fn fill_blanks(&self, base_config: &Config) -> Config {
+    pub fn fill_blanks(&self, base_config: &Config) -> Result<Config> {
         let mut config = base_config.clone();
         config.volume = self.volume.or(&base_config.volume);
+        self.fade_in.verify()?;
+        self.fade_out.verify()?;
+        config.fade_in = self.fade_in.or(&base_config.fade_in);
+        config.fade_out = self.fade_out.or(&base_config.fade_out);
         config.time_precision = self.time_precision.or(&config.time_precision);
         config.log_format = self.log_format.or(&base_config.log_format);
+        self.log_flush.verify()?;
+        config.log_flush = self.log_flush.or(&base_config.log_flush);
+        config.log_collision = self.log_collision.or(&base_config.log_collision);
+        if let Some(path) = self.output_path.as_ref() {
+            config.output_path = path.to_owned();
+        }
         config.interpreter = self.interpreter.or(&config.interpreter);
         config.audio_backend = self.audio_backend.or(&config.audio_backend);
+        config.audio_device = self.audio_device.or(&config.audio_device);
+        if let Some(&channels) = self.audio_channels.as_ref() {
+            if channels == 0 || channels > u16::MAX as u64 {
+                return Err(eyre!(
+                    "Config `audio_channels` should be between 1 and {} ({channels}).",
+                    u16::MAX
+                ));
+            }
+            config.audio_channels = Some(channels).into();
+        }
+        if let Some(&rate) = self.audio_sample_rate.as_ref() {
+            if rate == 0 || rate > u32::MAX as u64 {
+                return Err(eyre!(
+                    "Config `audio_sample_rate` should be a positive number ({rate})."
+                ));
+            }
+            config.audio_sample_rate = Some(rate).into();
+        }
+        config.audio_format = self.audio_format.or(&config.audio_format);
         config.stream_backend = self.stream_backend.or(&config.stream_backend);
+        if let Some(&rate) = self.stream_framerate.as_ref() {
+            if !rate.is_finite() || rate <= 0.0 {
+                return Err(eyre!(
+                    "Config `stream_framerate` should be a positive number ({rate})."
+                ));
+            }
+            config.stream_framerate = Some(rate).into();
+        }
         config.background = self.background.or(&config.background);
-        config
+        config.resource_lock = self.resource_lock.or(&config.resource_lock);
+        config.trace = self.trace.or(&config.trace);
+        if let Some(rate) = self.refresh_rate.as_f32() {
+            if !rate.is_finite() || rate <= 0.0 {
+                return Err(eyre!("Config `refresh_rate` should be a positive number."));
+            }
+            config.refresh_rate = rate;
+        }
+        Ok(config)
     }
 }
@@ -1,6 +1,7 @@
-use eyre::{eyre, Result};
+use eyre::{eyre, Context, Result};
 use serde::Serialize;
 use spin_sleep::{SpinSleeper, SpinStrategy};
+use std::path::Path;
 
 const APPROX_EQ_EPS: f64 = 1e-6;
 const SPIN_DURATION: u32 = 100_000_000; // equivalent to 100ms
@@ -67,3 +68,12 @@ pub trait Hash: Serialize {
         hex::encode(hasher.finalize())
     }
 }
+
+pub fn file_hash(path: &Path) -> Result<String> {
+    use sha2::{Digest, Sha256};
+    let content =
+        std::fs::read(path).wrap_err_with(|| format!("Failed to read file to hash ({path:?})."))?;
+    let mut hasher = Sha256::default();
+    hasher.update(&content);
+    Ok(hex::encode(hasher.finalize()))
+}
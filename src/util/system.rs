@@ -1,3 +1,4 @@
+use crate::resource::audio_output_devices;
 use std::fmt::{Debug, Formatter};
 use sysinfo::{CpuExt, SystemExt};
 
@@ -8,6 +9,7 @@ pub struct SystemInfo {
     cpu_cores: usize,
     memory_total: u64,
     memory_available: u64,
+    audio_devices: Vec<String>,
     pub(crate) renderer: String,
     pub(crate) hw_acceleration: String,
 }
@@ -33,6 +35,7 @@ impl SystemInfo {
             cpu_cores: sys.cpus().len(),
             memory_total: sys.total_memory(),
             memory_available: sys.available_memory(),
+            audio_devices: audio_output_devices().unwrap_or_default(),
             renderer: "(?)".to_owned(),
             hw_acceleration: "(?)".to_owned(),
         }
@@ -50,14 +53,19 @@ impl Debug for SystemInfo {
         write!(
             f,
             "System: {}\nCPU: {} (x{})\nMemory (GiB): {:.1} (available: {:.1})\n\
-            Renderer: {}\nHW acceleration: {}",
+            Renderer: {}\nHW acceleration: {}\nAudio devices:{}",
             self.system,
             self.cpu_brand,
             self.cpu_cores,
             self.memory_total as f64 / 2_f64.powi(30),
             self.memory_available as f64 / 2_f64.powi(30),
             self.renderer,
-            self.hw_acceleration
+            self.hw_acceleration,
+            self.audio_devices
+                .iter()
+                .enumerate()
+                .map(|(i, name)| format!("\n  {i}: {name}"))
+                .collect::<String>()
         )
     }
 }